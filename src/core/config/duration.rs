@@ -0,0 +1,225 @@
+//! Human-readable durations of the configuration, like `30m`, `1h30m` or `2d`.
+//!
+//! Bare integers are still read as seconds.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serializer};
+use std::time::Duration;
+
+/// Units of the duration grammar, from the largest to the smallest
+const UNITS: [(char, u64); 4] = [('d', 86400), ('h', 3600), ('m', 60), ('s', 1)];
+
+/// A duration as written in the configuration
+#[derive(Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum RawDuration {
+    /// A number of seconds
+    Seconds(u64),
+
+    /// A duration like `30m`, `1h30m` or `2d`
+    Readable(
+        #[schemars(regex(pattern = r"^\s*(\d+|(?=\d)(\d+d)?(\d+h)?(\d+m)?(\d+s)?)\s*$"))] String,
+    ),
+}
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum DurationError {
+    #[error("empty duration")]
+    Empty,
+
+    #[error("invalid duration '{0}' : expected a number of seconds or a duration like '1h30m'")]
+    Invalid(String),
+
+    #[error("invalid duration '{duration}' : unknown unit '{unit}' (expected d, h, m or s)")]
+    UnknownUnit { duration: String, unit: char },
+
+    #[error("invalid duration '{duration}' : unit '{unit}' is repeated or not in decreasing order")]
+    UnitOrder { duration: String, unit: char },
+
+    #[error("duration '{0}' is too long")]
+    Overflow(String),
+
+    #[error("duration must not be zero")]
+    Zero,
+}
+
+/// Parse a duration like `30m`, `1h30m` or `2d`, or a bare number of seconds.
+///
+/// Units must be in decreasing order, each at most once (`1h30m`, not `30m1h`)
+pub fn parse(raw: &str) -> Result<Duration, DurationError> {
+    let raw = raw.trim();
+    if raw.is_empty() {
+        return Err(DurationError::Empty);
+    }
+
+    // bare integers are seconds
+    if let Ok(seconds) = raw.parse::<u64>() {
+        return Ok(Duration::from_secs(seconds));
+    }
+
+    let overflow = || DurationError::Overflow(raw.to_string());
+    let mut seconds: u64 = 0;
+    let mut number = String::new();
+    // index in `UNITS` of the previous unit
+    let mut previous_unit = None;
+    for c in raw.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+
+        let unit_index =
+            UNITS
+                .iter()
+                .position(|(unit, _)| *unit == c)
+                .ok_or(DurationError::UnknownUnit {
+                    duration: raw.to_string(),
+                    unit: c,
+                })?;
+        if previous_unit.is_some_and(|previous| unit_index <= previous) {
+            return Err(DurationError::UnitOrder {
+                duration: raw.to_string(),
+                unit: c,
+            });
+        }
+        previous_unit = Some(unit_index);
+        let (_, unit_seconds) = UNITS[unit_index];
+        let value = number
+            .parse::<u64>()
+            .map_err(|_| DurationError::Invalid(raw.to_string()))?;
+        seconds = value
+            .checked_mul(unit_seconds)
+            .and_then(|s| seconds.checked_add(s))
+            .ok_or_else(overflow)?;
+        number.clear();
+    }
+
+    // a trailing number without unit, like '1h30'
+    if !number.is_empty() {
+        return Err(DurationError::Invalid(raw.to_string()));
+    }
+
+    Ok(Duration::from_secs(seconds))
+}
+
+/// Format a duration in the form read by [`parse`], like `1h30m`
+pub fn format(duration: &Duration) -> String {
+    let mut remaining = duration.as_secs();
+    if remaining == 0 {
+        return "0s".to_string();
+    }
+
+    let mut formatted = String::new();
+    for (unit, unit_seconds) in UNITS {
+        let value = remaining / unit_seconds;
+        if value > 0 {
+            formatted.push_str(&format!("{value}{unit}"));
+            remaining %= unit_seconds;
+        }
+    }
+    formatted
+}
+
+pub fn serialize<S>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_str(&format(duration))
+}
+
+pub fn deserialize<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match RawDuration::deserialize(deserializer)? {
+        RawDuration::Seconds(seconds) => Ok(Duration::from_secs(seconds)),
+        RawDuration::Readable(raw) => parse(&raw).map_err(serde::de::Error::custom),
+    }
+}
+
+/// Same as [`deserialize`], rejecting zero durations
+pub fn deserialize_non_zero<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let duration = deserialize(deserializer)?;
+    match duration.is_zero() {
+        true => Err(serde::de::Error::custom(DurationError::Zero)),
+        false => Ok(duration),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_durations() {
+        for (raw, seconds) in [
+            ("0", 0),
+            ("90", 90),
+            (" 15 ", 15),
+            ("0s", 0),
+            ("45s", 45),
+            ("30m", 1800),
+            ("1h30m", 5400),
+            ("2d", 172800),
+            ("1d2h3m4s", 93784),
+            ("1d4s", 86404),
+            ("90m", 5400),
+        ] {
+            assert_eq!(parse(raw), Ok(Duration::from_secs(seconds)), "{raw}");
+        }
+    }
+
+    #[test]
+    fn parse_invalid_durations() {
+        let invalid = |raw: &str| DurationError::Invalid(raw.to_string());
+        let unknown_unit = |raw: &str, unit| DurationError::UnknownUnit {
+            duration: raw.to_string(),
+            unit,
+        };
+        let unit_order = |raw: &str, unit| DurationError::UnitOrder {
+            duration: raw.to_string(),
+            unit,
+        };
+
+        for (raw, error) in [
+            ("", DurationError::Empty),
+            ("  ", DurationError::Empty),
+            ("h", invalid("h")),
+            ("1h30", invalid("1h30")),
+            ("-1s", unknown_unit("-1s", '-')),
+            ("10x", unknown_unit("10x", 'x')),
+            ("1h 30m", unknown_unit("1h 30m", ' ')),
+            ("1h1h", unit_order("1h1h", 'h')),
+            ("1s1d", unit_order("1s1d", 'd')),
+            ("30m1h", unit_order("30m1h", 'h')),
+            ("99999999999999999999d", invalid("99999999999999999999d")),
+            (
+                "999999999999999999d",
+                DurationError::Overflow("999999999999999999d".to_string()),
+            ),
+        ] {
+            assert_eq!(parse(raw), Err(error), "{raw}");
+        }
+    }
+
+    #[test]
+    fn format_round_trip() {
+        for (raw, formatted) in [
+            ("0", "0s"),
+            ("45s", "45s"),
+            ("90", "1m30s"),
+            ("90m", "1h30m"),
+            ("1h30m", "1h30m"),
+            ("2d", "2d"),
+            ("1d2h3m4s", "1d2h3m4s"),
+            ("1d4s", "1d4s"),
+        ] {
+            let duration = parse(raw).unwrap();
+            assert_eq!(format(&duration), formatted, "{raw}");
+            assert_eq!(parse(formatted), Ok(duration), "{raw}");
+        }
+    }
+}
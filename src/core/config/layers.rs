@@ -0,0 +1,179 @@
+//! Layered configuration : defaults, then YAML file, then environment variables,
+//! then command line flags. Each layer overrides the values set by the previous ones.
+
+use serde_yaml::{Mapping, Value};
+use std::{collections::BTreeMap, fmt, path::PathBuf};
+
+/// Prefix of the environment variables overriding the configuration
+pub const ENV_PREFIX: &str = "KUBESLEEPER_";
+
+/// Separator of the keys in environment variables names
+/// (like `KUBESLEEPER_CONTROLLER__SLEEPINESS_DURATION`)
+pub const ENV_KEY_SEPARATOR: &str = "__";
+
+/// Layer a configuration value comes from
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Layer {
+    Default,
+    File(PathBuf),
+    Env(String),
+    Cli(String),
+}
+
+impl fmt::Display for Layer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Layer::Default => write!(f, "default"),
+            Layer::File(path) => write!(f, "file {}", path.display()),
+            Layer::Env(var) => write!(f, "env {var}"),
+            Layer::Cli(flag) => write!(f, "cli {flag}"),
+        }
+    }
+}
+
+/// Layer of each configuration value, by dotted path (like 'controller.sleepiness_duration')
+pub type Sources = BTreeMap<String, Layer>;
+
+/// A value overriding the configuration, at a dotted path
+pub struct Override {
+    pub path: String,
+    pub value: Value,
+    pub layer: Layer,
+}
+
+/// Overrides from the environment variables like `KUBESLEEPER_CONTROLLER__SLEEPINESS_DURATION=600`.
+///
+/// Values are read as YAML, so lists can be set like `[kube-system, traefik]`.
+/// Variables without key separator (like `KUBESLEEPER_SERVICE_HOST`, set by Kubernetes for a
+/// Service named kubesleeper) are ignored.
+pub fn env_overrides(vars: impl Iterator<Item = (String, String)>) -> Vec<Override> {
+    let mut overrides: Vec<Override> = vars
+        .filter_map(|(var, raw)| {
+            let keys = var.strip_prefix(ENV_PREFIX)?;
+            if !keys.contains(ENV_KEY_SEPARATOR) {
+                return None;
+            }
+
+            let path = keys
+                .split(ENV_KEY_SEPARATOR)
+                .map(str::to_lowercase)
+                .collect::<Vec<_>>()
+                .join(".");
+            let value = serde_yaml::from_str(&raw).unwrap_or(Value::String(raw));
+
+            Some(Override {
+                path,
+                value,
+                layer: Layer::Env(var),
+            })
+        })
+        .collect();
+
+    // deterministic order, from the least to the most specific path
+    overrides.sort_by(|a, b| a.path.cmp(&b.path));
+    overrides
+}
+
+/// Merge `overlay` into `base`, mappings are merged key by key
+pub fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Mapping(base), Value::Mapping(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(base_value) => merge(base_value, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Set `value` at the dotted `path` of `root`, creating the missing mappings
+pub fn set(root: &mut Value, path: &str, value: Value) {
+    let mut current = root;
+    for key in path.split('.') {
+        if !current.is_mapping() {
+            *current = Value::Mapping(Mapping::new());
+        }
+        current = current
+            .as_mapping_mut()
+            .expect("Should be a mapping at this point")
+            .entry(Value::String(key.to_string()))
+            .or_insert(Value::Null);
+    }
+    *current = value;
+}
+
+/// Record `layer` as the source of all values of `value`, set at `path`
+pub fn record(sources: &mut Sources, path: &str, value: &Value, layer: &Layer) {
+    // values previously set under this path are replaced
+    sources.retain(|p, _| !(p == path || p.starts_with(&format!("{path}."))));
+
+    match value {
+        Value::Mapping(mapping) if !mapping.is_empty() => {
+            for (key, value) in mapping {
+                let key = key.as_str().map(str::to_string).unwrap_or_default();
+                let child = match path.is_empty() {
+                    true => key,
+                    false => format!("{path}.{key}"),
+                };
+                record(sources, &child, value, layer);
+            }
+        }
+        _ => {
+            sources.insert(path.to_string(), layer.clone());
+        }
+    }
+}
+
+/// Render `value` as YAML, each value followed by a comment naming its layer
+pub fn render(value: &Value, sources: &Sources) -> String {
+    let mut rendered = String::new();
+    render_mapping(&mut rendered, value, sources, "", 0);
+    rendered
+}
+
+fn render_mapping(
+    rendered: &mut String,
+    value: &Value,
+    sources: &Sources,
+    path: &str,
+    indent: usize,
+) {
+    let Some(mapping) = value.as_mapping() else {
+        return;
+    };
+
+    for (key, value) in mapping {
+        let key = key.as_str().unwrap_or_default();
+        let child = match path.is_empty() {
+            true => key.to_string(),
+            false => format!("{path}.{key}"),
+        };
+        let padding = " ".repeat(indent);
+
+        match value {
+            Value::Mapping(m) if !m.is_empty() => {
+                rendered.push_str(&format!("{padding}{key}:\n"));
+                render_mapping(rendered, value, sources, &child, indent + 2);
+            }
+            _ => {
+                let source = sources.get(&child).unwrap_or(&Layer::Default);
+                let raw = serde_yaml::to_string(value).unwrap_or_default();
+                let raw = raw.trim_end();
+                // sequence items are rendered as a block, even if there is only one
+                if raw.contains('\n') || matches!(value, Value::Sequence(s) if !s.is_empty()) {
+                    rendered.push_str(&format!("{padding}{key}: # {source}\n"));
+                    for line in raw.lines() {
+                        rendered.push_str(&format!("{padding}  {line}\n"));
+                    }
+                } else {
+                    rendered.push_str(&format!("{padding}{key}: {raw} # {source}\n"));
+                }
+            }
+        }
+    }
+}
@@ -1,13 +1,35 @@
-use serde::{Deserialize, Deserializer, Serialize};
-use std::num::{NonZeroU16, NonZeroU32};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::num::NonZeroU16;
 use std::path::PathBuf;
 use std::str::FromStr;
 use std::time::Duration;
 use tracing::{debug, warn};
 
-const DEFAULT_CONFIG_FILE_PATH: &str = "kubesleeper.yaml";
+use crate::core::{
+    resource::{
+        management::Management, namespaces::Namespaces, redirection::Redirection,
+        scalable::ScalableKind,
+    },
+    state::{
+        calendar::{Calendar, CalendarError},
+        group::GroupBy,
+        initial_state::InitialState,
+        schedule::{Schedule, Timezone},
+    },
+};
 
-#[derive(Default, Serialize, Debug, Deserialize, Clone)]
+pub mod duration;
+pub mod layers;
+pub mod reload;
+pub mod validate;
+
+use layers::{Layer, Override, Sources};
+
+pub const DEFAULT_CONFIG_FILE_PATH: &str = "kubesleeper.yaml";
+
+#[derive(Default, Serialize, Debug, Deserialize, Clone, JsonSchema)]
 #[serde(deny_unknown_fields)]
 // TODO: Rename ServerConfig to Server and rename ControllerConfig to another name more explicit than "controller" for key
 pub struct Config {
@@ -16,10 +38,28 @@ pub struct Config {
 
     #[serde(default)]
     pub controller: ControllerConfig,
+
+    /// Layer of each value
+    #[serde(skip)]
+    pub sources: Sources,
+
+    /// Holidays of the `controller.holidays` calendar file
+    #[serde(skip)]
+    pub calendar: Calendar,
 }
 
-#[derive(Serialize, Debug, Deserialize, Clone)]
-#[serde(deny_unknown_fields)]
+impl Config {
+    /// Dump the configuration as YAML, with the layer of each value
+    pub fn dump(&self) -> String {
+        match serde_yaml::to_value(self) {
+            Ok(value) => layers::render(&value, &self.sources),
+            Err(_) => format!("{self:?}"),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Deserialize, Clone, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
 pub struct ServerConfig {
     /// Port of the kubesleeper server
     pub port: NonZeroU16,
@@ -33,34 +73,95 @@ impl Default for ServerConfig {
     }
 }
 
-#[derive(Serialize, Debug, Deserialize, Clone)]
-#[serde(deny_unknown_fields)]
+#[derive(Serialize, Debug, Deserialize, Clone, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
 pub struct ControllerConfig {
-    /// Sleepiness duration in second
-    #[serde(deserialize_with = "deserialize_sleepiness_duration")]
+    /// Sleepiness duration, like '15m' (bare integers are seconds)
+    #[serde(with = "duration")]
+    #[schemars(with = "duration::RawDuration")]
     pub sleepiness_duration: Duration,
 
-    /// Time between two activity check in second
-    pub refresh_interval: NonZeroU32,
+    /// Time between two activity check, like '5s' (bare integers are seconds)
+    #[serde(
+        serialize_with = "duration::serialize",
+        deserialize_with = "duration::deserialize_non_zero"
+    )]
+    #[schemars(with = "duration::RawDuration")]
+    pub refresh_interval: Duration,
+
+    /// Minimum time a group stays awake once woken up, like '10m' (bare integers are seconds)
+    #[serde(with = "duration")]
+    #[schemars(with = "duration::RawDuration")]
+    pub min_awake_duration: Duration,
+
+    /// Time after each transition during which a group can't fall asleep, like '1m'
+    /// (bare integers are seconds)
+    #[serde(with = "duration")]
+    #[schemars(with = "duration::RawDuration")]
+    pub cooldown: Duration,
+
+    /// Grouping of resources without a `kubesleeper/group` annotation
+    #[serde(default)]
+    pub group_by: GroupBy,
+
+    /// Redirection of the traffic of asleep Services to kubesleeper
+    #[serde(default)]
+    pub redirection: Redirection,
+
+    /// Other kinds of resources to scale through their `/scale` subresource
+    #[serde(default)]
+    pub scalable_kinds: Vec<ScalableKind>,
+
+    /// Resources managed when they don't have a `kubesleeper/enabled` annotation
+    #[serde(default)]
+    pub management: Management,
+
+    /// Namespaces whose resources are managed
+    #[serde(default)]
+    pub namespaces: Namespaces,
+
+    /// Label selector of the managed resources (like 'env in (dev,preview)')
+    #[serde(default)]
+    pub selector: Option<String>,
+
+    /// State of the groups when kubesleeper starts
+    #[serde(default)]
+    pub initial_state: InitialState,
+
+    /// Times at which all groups are set asleep or awake
+    #[serde(default)]
+    pub schedules: Vec<Schedule>,
+
+    /// Timezone of the schedules, as an IANA time zone name (like 'Europe/Paris')
+    #[serde(default)]
+    pub timezone: Timezone,
+
+    /// iCalendar (.ics) file of the holidays, during which all groups are asleep
+    #[serde(default)]
+    pub holidays: Option<PathBuf>,
 }
 
 impl Default for ControllerConfig {
     fn default() -> Self {
         ControllerConfig {
             sleepiness_duration: const { Duration::new(15, 0) },
-            refresh_interval: const { NonZeroU32::new(5).unwrap() },
+            refresh_interval: const { Duration::new(5, 0) },
+            min_awake_duration: Duration::ZERO,
+            cooldown: Duration::ZERO,
+            group_by: GroupBy::default(),
+            redirection: Redirection::default(),
+            scalable_kinds: Vec::new(),
+            management: Management::default(),
+            namespaces: Namespaces::default(),
+            selector: None,
+            initial_state: InitialState::default(),
+            schedules: Vec::new(),
+            timezone: Timezone::default(),
+            holidays: None,
         }
     }
 }
 
-fn deserialize_sleepiness_duration<'de, D>(deserializer: D) -> Result<Duration, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    let seconds = u64::deserialize(deserializer)?;
-    Ok(Duration::from_secs(seconds))
-}
-
 #[derive(Debug, thiserror::Error)]
 pub enum ConfigError {
     #[error("Can't open {path} : {err}.")]
@@ -74,9 +175,25 @@ pub enum ConfigError {
 
     #[error("File not found : '{0}'")]
     FileNotFoud(String),
+
+    #[error("{0} error(s) found in configuration file")]
+    Invalid(usize),
+
+    #[error(transparent)]
+    Calendar(#[from] CalendarError),
+
+    #[error("Invalid value '{value}' for '{path}' ({layer}) : {err}")]
+    InvalidOverride {
+        layer: Layer,
+        path: String,
+        value: String,
+        err: serde_yaml::Error,
+    },
 }
 
-pub fn parse(path: Option<PathBuf>) -> Result<Config, ConfigError> {
+/// Load the configuration from its layers : defaults, then YAML file at `path`,
+/// then environment variables, then `cli_overrides`
+pub fn parse(path: Option<PathBuf>, cli_overrides: Vec<Override>) -> Result<Config, ConfigError> {
     let path: Option<PathBuf> = match path {
         // Config file path explicitly set
         Some(p) => {
@@ -119,15 +236,55 @@ pub fn parse(path: Option<PathBuf>) -> Result<Config, ConfigError> {
         }
     };
 
-    let config = match path {
-        None => Config::default(),
-        Some(path) => {
-            let file =
-                std::fs::File::open(&path).map_err(|err| ConfigError::IOError { path, err })?;
+    // --- defaults
+    let mut value = serde_yaml::to_value(Config::default())?;
+    let mut sources = Sources::new();
+    layers::record(&mut sources, "", &value, &Layer::Default);
 
-            serde_yaml::from_reader(file)?
+    // --- YAML file
+    if let Some(path) = path {
+        let raw = std::fs::read_to_string(&path).map_err(|err| ConfigError::IOError {
+            path: path.clone(),
+            err,
+        })?;
+
+        let file_value: Value = serde_yaml::from_str(&raw)?;
+        if !file_value.is_null() {
+            // parsed alone first, so errors are reported with their line and column
+            serde_yaml::from_str::<Config>(&raw)?;
+
+            layers::record(&mut sources, "", &file_value, &Layer::File(path));
+            layers::merge(&mut value, file_value);
         }
-    };
+    }
+
+    // --- environment variables, then command line
+    for o in layers::env_overrides(std::env::vars())
+        .into_iter()
+        .chain(cli_overrides)
+    {
+        debug!("Config value '{}' set from {}", o.path, o.layer);
+        layers::set(&mut value, &o.path, o.value.clone());
+
+        // checked one by one, so an invalid value is reported with its origin
+        serde_yaml::from_value::<Config>(value.clone()).map_err(|err| {
+            ConfigError::InvalidOverride {
+                layer: o.layer.clone(),
+                path: o.path.clone(),
+                value: serde_yaml::to_string(&o.value)
+                    .unwrap_or_default()
+                    .trim()
+                    .to_string(),
+                err,
+            }
+        })?;
+        layers::record(&mut sources, &o.path, &o.value, &o.layer);
+    }
 
+    let mut config: Config = serde_yaml::from_value(value)?;
+    config.sources = sources;
+    if let Some(holidays) = &config.controller.holidays {
+        config.calendar = Calendar::load(holidays, config.controller.timezone)?;
+    }
     Ok(config)
 }
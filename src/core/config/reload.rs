@@ -0,0 +1,141 @@
+use std::{path::PathBuf, time::Duration};
+use tokio::signal::unix::{SignalKind, signal};
+use tracing::{debug, error, info, warn};
+
+use crate::core::{
+    config::{self, Config, DEFAULT_CONFIG_FILE_PATH},
+    resource::{
+        label_selector::LABEL_SELECTOR, management::MANAGEMENT, namespaces::NAMESPACES,
+        redirection::REDIRECTION, scalable::SCALABLE_KINDS,
+    },
+    state::{
+        calendar::CALENDAR,
+        schedule::{self, SCHEDULES, TIMEZONE},
+        state::{
+            COOLDOWN, GROUP_BY, MIN_AWAKE_DURATION, REFRESH_INTERVAL, SLEEPINESS_DURATION,
+            replace_schedules, reschedule,
+        },
+    },
+};
+
+/// Time between two checks of the configuration file content
+const WATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Set the global values of the controller from `config`
+pub fn apply(config: &Config) {
+    SLEEPINESS_DURATION.set(config.controller.sleepiness_duration);
+    REFRESH_INTERVAL.set(config.controller.refresh_interval);
+    MIN_AWAKE_DURATION.set(config.controller.min_awake_duration);
+    COOLDOWN.set(config.controller.cooldown);
+    GROUP_BY.set(config.controller.group_by);
+    REDIRECTION.set(config.controller.redirection);
+    SCALABLE_KINDS.set(config.controller.scalable_kinds.clone());
+    MANAGEMENT.set(config.controller.management);
+    NAMESPACES.set(config.controller.namespaces.clone());
+    LABEL_SELECTOR.set(config.controller.selector.clone());
+    SCHEDULES.set(config.controller.schedules.clone());
+    TIMEZONE.set(config.controller.timezone);
+    CALENDAR.set(config.calendar.clone());
+}
+
+/// Content of the holidays calendar file of `config`, if any
+fn read_holidays(config: &Config) -> Option<Vec<u8>> {
+    std::fs::read(config.controller.holidays.as_ref()?).ok()
+}
+
+/// Reload the configuration when its file or holidays calendar content changes (like a
+/// ConfigMap update, which swaps a symlink) or when SIGHUP is received.
+///
+/// An invalid configuration is rejected, the running one is kept.
+pub async fn watch(path: Option<PathBuf>, mut current: Config) {
+    let watched_path = path
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_CONFIG_FILE_PATH));
+    let mut content = std::fs::read(&watched_path).ok();
+    let mut holidays = read_holidays(&current);
+
+    let mut hangup = signal(SignalKind::hangup())
+        .inspect_err(|e| warn!("Can't listen to SIGHUP, only watching config file : {e}"))
+        .ok();
+    let mut interval = tokio::time::interval(WATCH_INTERVAL);
+
+    loop {
+        tokio::select! {
+            Some(()) = async {
+                match hangup.as_mut() {
+                    Some(hangup) => hangup.recv().await,
+                    None => std::future::pending().await,
+                }
+            } => info!("SIGHUP received : reloading configuration"),
+            _ = interval.tick() => {
+                let new_content = std::fs::read(&watched_path).ok();
+                let new_holidays = read_holidays(&current);
+                if new_content != content {
+                    info!("Configuration file {} changed : reloading configuration", watched_path.display());
+                } else if new_holidays != holidays {
+                    info!("Holidays calendar changed : reloading configuration");
+                } else {
+                    continue;
+                }
+                content = new_content;
+            }
+        }
+
+        let new = match config::parse(path.clone(), Vec::new()) {
+            Ok(new) => new,
+            Err(e) => {
+                error!("Invalid configuration, keeping the running one : {e}");
+                continue;
+            }
+        };
+        current = reload(current, new).await;
+        holidays = read_holidays(&current);
+    }
+}
+
+/// Apply `new` configuration in place of `current`, returning the running configuration
+async fn reload(current: Config, mut new: Config) -> Config {
+    // the server can't change without being rebinded
+    if new.server.port != current.server.port {
+        warn!(
+            "Server port can't change without a restart : keeping port {}",
+            current.server.port
+        );
+        new.server = current.server.clone();
+    }
+
+    if serde_yaml::to_string(&new).ok() == serde_yaml::to_string(&current).ok()
+        && new.calendar == current.calendar
+    {
+        debug!("Configuration unchanged");
+        return current;
+    }
+
+    if new.controller.refresh_interval != current.controller.refresh_interval
+        && let Err(e) = reschedule(new.controller.refresh_interval).await
+    {
+        error!("Failed to change refresh interval, keeping the running configuration : {e}");
+        return current;
+    }
+
+    let schedules_changed = new.controller.schedules != current.controller.schedules
+        || new.controller.timezone != current.controller.timezone;
+    if schedules_changed
+        && let Err(e) = replace_schedules(&new.controller.schedules, new.controller.timezone).await
+    {
+        error!("Failed to change schedules, keeping the running configuration : {e}");
+        return current;
+    }
+
+    apply(&new);
+    if schedules_changed {
+        // the held state follows the new schedules from their next run
+        let now = k8s_openapi::chrono::Utc::now();
+        match schedule::current(&new.controller.schedules, new.controller.timezone, now) {
+            Some(current) => schedule::set_hold(current),
+            None => schedule::clear_hold(),
+        }
+    }
+    info!("Configuration reloaded");
+    new
+}
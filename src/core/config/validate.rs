@@ -0,0 +1,378 @@
+//! Validation of a configuration file, reporting all its errors with their location
+//! instead of stopping at the first one.
+
+use serde_yaml::{Mapping, Value};
+use std::{collections::HashMap, fmt};
+use yaml_rust2::{
+    Event,
+    parser::{MarkedEventReceiver, Parser},
+    scanner::Marker,
+};
+
+use crate::core::config::{Config, layers};
+
+/// An error of a configuration file
+#[derive(Debug)]
+pub struct ValidationError {
+    /// Dotted path of the invalid key, empty for the whole document
+    pub path: String,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.path.is_empty() {
+            true => write!(f, "{}:{} : {}", self.line, self.column, self.message),
+            false => write!(
+                f,
+                "{}:{} : '{}' : {}",
+                self.line, self.column, self.path, self.message
+            ),
+        }
+    }
+}
+
+/// Check the YAML configuration `raw`, returning all of its errors
+pub fn validate(raw: &str) -> Vec<ValidationError> {
+    let value: Value = match serde_yaml::from_str(raw) {
+        Ok(value) => value,
+        // syntax error : the rest of the document can't be read
+        Err(e) => return vec![document_error(e)],
+    };
+
+    let root = match value {
+        // empty file
+        Value::Null => return Vec::new(),
+        Value::Mapping(root) => root,
+        _ => {
+            return vec![ValidationError {
+                path: String::new(),
+                line: 1,
+                column: 1,
+                message: "expected a mapping of configuration keys".to_string(),
+            }];
+        }
+    };
+
+    let positions = KeyPositions::of(raw);
+    let mut errors = Vec::new();
+    check_mapping(&mut errors, &positions, "", root);
+
+    // errors only visible on the whole document
+    if errors.is_empty()
+        && let Err(e) = serde_yaml::from_str::<Config>(raw)
+    {
+        errors.push(document_error(e));
+    }
+    errors
+}
+
+fn document_error(err: serde_yaml::Error) -> ValidationError {
+    let (line, column) = err
+        .location()
+        .map(|location| (location.line(), location.column()))
+        .unwrap_or((1, 1));
+    ValidationError {
+        path: String::new(),
+        line,
+        column,
+        message: err.to_string(),
+    }
+}
+
+fn check_mapping(
+    errors: &mut Vec<ValidationError>,
+    positions: &KeyPositions,
+    path: &str,
+    mapping: Mapping,
+) {
+    for (key, value) in mapping {
+        let key = match key {
+            Value::String(key) => key,
+            key => serde_yaml::to_string(&key)
+                .unwrap_or_default()
+                .trim_end()
+                .to_string(),
+        };
+        let child = match path.is_empty() {
+            true => key,
+            false => format!("{path}.{key}"),
+        };
+        check(errors, positions, &child, value);
+    }
+}
+
+/// Check `value` alone at `path`, then its children to find the deepest invalid keys
+fn check(errors: &mut Vec<ValidationError>, positions: &KeyPositions, path: &str, value: Value) {
+    let mut alone = Value::Mapping(Mapping::new());
+    layers::set(&mut alone, path, value.clone());
+    let Err(err) = serde_yaml::from_value::<Config>(alone) else {
+        return;
+    };
+
+    let before = errors.len();
+    match value {
+        Value::Mapping(mapping) if !mapping.is_empty() => {
+            check_mapping(errors, positions, path, mapping)
+        }
+        Value::Sequence(items) if items.len() > 1 => check_items(errors, positions, path, items),
+        _ => {}
+    }
+
+    // no child is invalid by itself : the error is on this key
+    if errors.len() == before {
+        push(errors, positions, path, err);
+    }
+}
+
+/// Check each item of the sequence at `path` alone
+fn check_items(
+    errors: &mut Vec<ValidationError>,
+    positions: &KeyPositions,
+    path: &str,
+    items: Vec<Value>,
+) {
+    for (index, item) in items.into_iter().enumerate() {
+        let mut alone = Value::Mapping(Mapping::new());
+        layers::set(&mut alone, path, Value::Sequence(vec![item]));
+        if let Err(err) = serde_yaml::from_value::<Config>(alone) {
+            push(errors, positions, &format!("{path}.{index}"), err);
+        }
+    }
+}
+
+fn push(
+    errors: &mut Vec<ValidationError>,
+    positions: &KeyPositions,
+    path: &str,
+    err: serde_yaml::Error,
+) {
+    let (line, column) = positions.get(path);
+    errors.push(ValidationError {
+        path: path.to_string(),
+        line,
+        column,
+        message: err.to_string(),
+    });
+}
+
+/// Line and column of each key and sequence item of a YAML document, by dotted path
+struct KeyPositions(HashMap<String, (usize, usize)>);
+
+impl KeyPositions {
+    fn of(raw: &str) -> Self {
+        let mut locator = Locator {
+            stack: Vec::new(),
+            positions: HashMap::new(),
+            mapping_item: None,
+        };
+        // syntax errors are already reported by serde_yaml
+        let _ = Parser::new_from_str(raw).load(&mut locator, false);
+        KeyPositions(locator.positions)
+    }
+
+    /// Position of the key at `path`, or of its closest parent if unknown
+    fn get(&self, path: &str) -> (usize, usize) {
+        let mut path = path;
+        loop {
+            if let Some(position) = self.0.get(path) {
+                return *position;
+            }
+            match path.rsplit_once('.') {
+                Some((parent, _)) => path = parent,
+                None => return (1, 1),
+            }
+        }
+    }
+}
+
+enum Frame {
+    /// A mapping, with the key whose value is being read
+    Mapping {
+        path: String,
+        key: Option<String>,
+    },
+    Sequence {
+        path: String,
+        index: usize,
+    },
+}
+
+struct Locator {
+    stack: Vec<Frame>,
+    positions: HashMap<String, (usize, usize)>,
+    /// Path of a mapping item whose first key is expected, located at this key
+    /// (the marker of a block mapping start isn't at its beginning)
+    mapping_item: Option<String>,
+}
+
+impl Locator {
+    /// Path of the value starting now
+    fn value_path(&self) -> String {
+        let (path, key) = match self.stack.last() {
+            Some(Frame::Mapping {
+                path,
+                key: Some(key),
+            }) => (path, key.clone()),
+            Some(Frame::Sequence { path, index }) => (path, index.to_string()),
+            _ => return String::new(),
+        };
+        match path.is_empty() {
+            true => key,
+            false => format!("{path}.{key}"),
+        }
+    }
+
+    /// Record the position of a value starting now, if it is a sequence item
+    fn item_start(&mut self, mark: Marker) {
+        if let Some(Frame::Sequence { .. }) = self.stack.last() {
+            // columns of the marker start at 0
+            let position = (mark.line(), mark.col() + 1);
+            self.positions.insert(self.value_path(), position);
+        }
+    }
+
+    /// A value of the current mapping or sequence has been read
+    fn value_read(&mut self) {
+        match self.stack.last_mut() {
+            Some(Frame::Mapping { key, .. }) => *key = None,
+            Some(Frame::Sequence { index, .. }) => *index += 1,
+            None => {}
+        }
+    }
+}
+
+impl MarkedEventReceiver for Locator {
+    fn on_event(&mut self, ev: Event, mark: Marker) {
+        match ev {
+            Event::MappingStart(..) => {
+                let path = self.value_path();
+                if let Some(Frame::Sequence { .. }) = self.stack.last() {
+                    self.mapping_item = Some(path.clone());
+                }
+                self.stack.push(Frame::Mapping { path, key: None });
+            }
+            Event::SequenceStart(..) => {
+                self.item_start(mark);
+                let path = self.value_path();
+                self.stack.push(Frame::Sequence { path, index: 0 });
+            }
+            Event::MappingEnd | Event::SequenceEnd => {
+                // an empty mapping item
+                self.mapping_item = None;
+                self.stack.pop();
+                self.value_read();
+            }
+            Event::Scalar(scalar, ..) => match self.stack.last_mut() {
+                Some(Frame::Mapping {
+                    key: key @ None, ..
+                }) => {
+                    *key = Some(scalar);
+                    // columns of the marker start at 0
+                    let position = (mark.line(), mark.col() + 1);
+                    self.positions.insert(self.value_path(), position);
+                    if let Some(item) = self.mapping_item.take() {
+                        self.positions.insert(item, position);
+                    }
+                }
+                _ => {
+                    self.item_start(mark);
+                    self.value_read()
+                }
+            },
+            Event::Alias(_) => {
+                self.item_start(mark);
+                self.value_read()
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// (path, line, column) of each error of `raw`
+    fn locate(raw: &str) -> Vec<(String, usize, usize)> {
+        validate(raw)
+            .into_iter()
+            .map(|e| (e.path, e.line, e.column))
+            .collect()
+    }
+
+    fn error(path: &str, line: usize, column: usize) -> (String, usize, usize) {
+        (path.to_string(), line, column)
+    }
+
+    #[test]
+    fn locate_nested_key() {
+        let raw = "\
+server:
+  port: 8080
+controller:
+  refresh_interval: 5s
+  sleepiness_duration: 10x
+";
+        assert_eq!(locate(raw), [error("controller.sleepiness_duration", 5, 3)]);
+    }
+
+    #[test]
+    fn locate_sequence_item() {
+        let raw = "\
+controller:
+  scalable_kinds:
+    - group: argoproj.io
+      version: v1alpha1
+      kind: Rollout
+    - group: example.com
+      version: v1
+";
+        assert_eq!(locate(raw), [error("controller.scalable_kinds.1", 6, 7)]);
+
+        let raw = "\
+controller:
+  scalable_kinds: [{group: a, version: v1, kind: A}, {group: b, version: v1}]
+";
+        assert_eq!(locate(raw), [error("controller.scalable_kinds.1", 2, 55)]);
+    }
+
+    #[test]
+    fn locate_unknown_field() {
+        let raw = "\
+controller:
+  sleepiness_duration: 10m
+  cooldwn: 5m
+";
+        assert_eq!(locate(raw), [error("controller.cooldwn", 3, 3)]);
+    }
+
+    #[test]
+    fn locate_type_error() {
+        let raw = "\
+server:
+  port: eighty
+controller:
+  group_by: foo
+";
+        assert_eq!(
+            locate(raw),
+            [
+                error("server.port", 2, 3),
+                error("controller.group_by", 4, 3)
+            ]
+        );
+    }
+
+    #[test]
+    fn locate_syntax_error() {
+        let raw = "\
+controller:
+  sleepiness_duration: 10m
+ refresh_interval: 5s
+";
+        assert_eq!(locate(raw), [error("", 3, 2)]);
+    }
+}
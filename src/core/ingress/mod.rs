@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 
-use k8s_openapi::api::core::v1::Pod;
-use kube::{ResourceExt, runtime::reflector::Lookup};
+use k8s_openapi::api::{core::v1::Pod, networking::v1::Ingress};
+use kube::{Api, Client, ResourceExt, api::ListParams, runtime::reflector::Lookup};
 use reqwest;
 use tracing::debug;
 
@@ -123,3 +123,40 @@ pub async fn get_prometheus_raw_metrics_dump(pod: &Pod) -> Result<String, Ingres
 
     Ok(reqwest::get(url).await?.text().await?)
 }
+
+/// Services ({namespace}, {name}) targeted by the Ingress rules of each host
+pub async fn get_ingress_hosts(
+    client: &Client,
+) -> Result<HashMap<String, Vec<(String, String)>>, IngressError> {
+    let ingresses = Api::<Ingress>::all(client.clone())
+        .list(&ListParams::default())
+        .await?;
+
+    let mut hosts: HashMap<String, Vec<(String, String)>> = HashMap::new();
+    for ingress in ingresses {
+        let namespace = ResourceExt::namespace(&ingress).unwrap_or("default".into());
+
+        let rules = ingress
+            .spec
+            .iter()
+            .flat_map(|spec| spec.rules.iter().flatten());
+        for rule in rules {
+            let Some(host) = &rule.host else {
+                continue;
+            };
+            let services = hosts.entry(host.to_ascii_lowercase()).or_default();
+            let backends = rule
+                .http
+                .iter()
+                .flat_map(|http| http.paths.iter())
+                .filter_map(|path| path.backend.service.as_ref());
+            for backend in backends {
+                let service = (namespace.clone(), backend.name.clone());
+                if !services.contains(&service) {
+                    services.push(service);
+                }
+            }
+        }
+    }
+    Ok(hosts)
+}
@@ -1,15 +1,277 @@
-use crate::core::ingress::{IngressType, error::IngressError};
+use crate::core::{
+    ingress::{IngressType, error::IngressError, get_ingress_hosts},
+    resource::service::Service,
+};
 
 use k8s_openapi::api::core::v1::Pod;
-use kube::{Api, Client, Config, api::ListParams};
+use kube::{
+    Api, Client, Config, ResourceExt,
+    api::{ApiResource, DynamicObject, GroupVersionKind, ListParams},
+};
 use regex::Regex;
+use serde_json::Value;
 use std::collections::HashMap;
+use tracing::{debug, warn};
 
 const SELECTOR: &str = "app.kubernetes.io/name=traefik";
 const TRAEFIK_REGEXP_METRIC: &str = r#"traefik_service_requests_total\{.*service="(.+)".*\} (\d+)"#;
+const TRAEFIK_REGEXP_HOST: &str = r"\bHost\(([^)]*)\)";
+
+const KUBERNETES_PROVIDER: &str = "kubernetes";
+const KUBERNETES_CRD_PROVIDER: &str = "kubernetescrd";
+
+// 'traefik.containo.us' is the deprecated group used before Traefik v3
+const TRAEFIK_CRD_GROUPS: [&str; 2] = ["traefik.io", "traefik.containo.us"];
+const TRAEFIK_CRD_VERSION: &str = "v1alpha1";
+const TRAEFIK_SERVICE_KIND: &str = "TraefikService";
+const INGRESS_ROUTE_KIND: &str = "IngressRoute";
+
+/// Max depth of nested TraefikServices, to prevent infinite loops on circular references
+const MAX_RESOLUTION_DEPTH: usize = 10;
 
 pub struct Traefik {}
 
+/// Reference to a Service (or a TraefikService) as written in Traefik CRDs
+#[derive(Debug, Clone)]
+struct ServiceRef {
+    namespace: String,
+    name: String,
+    is_traefik_service: bool,
+}
+
+impl ServiceRef {
+    fn from_value(value: &Value, default_namespace: &str) -> Option<ServiceRef> {
+        Some(ServiceRef {
+            namespace: value["namespace"]
+                .as_str()
+                .unwrap_or(default_namespace)
+                .to_string(),
+            name: value["name"].as_str()?.to_string(),
+            is_traefik_service: value["kind"].as_str() == Some(TRAEFIK_SERVICE_KIND),
+        })
+    }
+
+    fn from_values(values: &Value, default_namespace: &str) -> Vec<ServiceRef> {
+        values
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|v| ServiceRef::from_value(v, default_namespace))
+            .collect()
+    }
+}
+
+/// Resolve Traefik service names (like `default-blue-80@kubernetes`) to the ids
+/// ({namespace}/{name}) of the Kubernetes Services they route to.
+///
+/// Supported Traefik services are :
+/// - services of Ingresses and IngressRoutes : `{namespace}-{name}-{port}@kubernetes(crd)`
+/// - TraefikServices (weighted and mirroring) : `{namespace}-{name}@kubernetescrd`
+/// - weighted services generated for IngressRoutes with several services : `{namespace}-{name}-{hash}@kubernetescrd`
+///
+/// Hosts are also resolved, from the rules of Ingresses and the `Host` matchers of IngressRoutes.
+#[derive(Debug, Default)]
+pub struct TraefikServiceResolver {
+    /// (namespace, name) of the known Services
+    services: Vec<(String, String)>,
+
+    /// Children of each TraefikService, by '{namespace}-{name}'
+    traefik_services: HashMap<String, Vec<ServiceRef>>,
+
+    /// Services of each IngressRoute, by '{namespace}-{name}'
+    ingress_routes: HashMap<String, Vec<ServiceRef>>,
+
+    /// Services routed for each host by Ingress rules and IngressRoute `Host` matchers
+    hosts: HashMap<String, Vec<ServiceRef>>,
+}
+
+impl TraefikServiceResolver {
+    /// Build a resolver for `services`, fetching Traefik CRDs from the cluster.
+    ///
+    /// Traefik CRDs are optional : if they can't be listed, only services of
+    /// Ingresses and single service IngressRoutes are resolved.
+    pub async fn new(services: &[Service]) -> Result<TraefikServiceResolver, IngressError> {
+        let client = Client::try_default().await?;
+
+        let mut resolver = TraefikServiceResolver::without_crds(services);
+
+        match get_ingress_hosts(&client).await {
+            Ok(hosts) => {
+                for (host, services) in hosts {
+                    let refs = services.into_iter().map(|(namespace, name)| ServiceRef {
+                        namespace,
+                        name,
+                        is_traefik_service: false,
+                    });
+                    resolver.hosts.entry(host).or_default().extend(refs);
+                }
+            }
+            Err(e) => warn!("Can't list Ingresses, their hosts won't be resolved : {e}"),
+        };
+        for ts in list_traefik_crd(&client, TRAEFIK_SERVICE_KIND).await {
+            resolver.add_traefik_service(&ts);
+        }
+        for ir in list_traefik_crd(&client, INGRESS_ROUTE_KIND).await {
+            resolver.add_ingress_route(&ir);
+        }
+        Ok(resolver)
+    }
+
+    /// Add the children of a TraefikService (weighted and mirroring services)
+    fn add_traefik_service(&mut self, ts: &DynamicObject) {
+        let namespace = ts.namespace().unwrap_or_default();
+        let spec = &ts.data["spec"];
+
+        let mut children = ServiceRef::from_values(&spec["weighted"]["services"], &namespace);
+        children.extend(ServiceRef::from_value(&spec["mirroring"], &namespace));
+        children.extend(ServiceRef::from_values(
+            &spec["mirroring"]["mirrors"],
+            &namespace,
+        ));
+
+        self.traefik_services
+            .insert(format!("{namespace}-{}", ts.name_any()), children);
+    }
+
+    /// Add the services of an IngressRoute, and the hosts of its routes
+    fn add_ingress_route(&mut self, ir: &DynamicObject) {
+        let namespace = ir.namespace().unwrap_or_default();
+        let mut children = Vec::new();
+        for route in ir.data["spec"]["routes"].as_array().into_iter().flatten() {
+            let services = ServiceRef::from_values(&route["services"], &namespace);
+            for host in rule_hosts(route["match"].as_str().unwrap_or_default()) {
+                self.hosts
+                    .entry(host)
+                    .or_default()
+                    .extend(services.iter().cloned());
+            }
+            children.extend(services);
+        }
+        self.ingress_routes
+            .insert(format!("{namespace}-{}", ir.name_any()), children);
+    }
+
+    /// Build a resolver for `services` only, without Traefik CRDs (and without the cluster)
+    pub fn without_crds(services: &[Service]) -> TraefikServiceResolver {
+        TraefikServiceResolver {
+            services: services
+                .iter()
+                .map(|s| (s.namespace.clone(), s.name.clone()))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Ids ({namespace}/{name}) of the Services targeted by a Traefik service
+    pub fn resolve(&self, traefik_service: &str) -> Vec<String> {
+        let mut ids = Vec::new();
+        match traefik_service.rsplit_once('@') {
+            Some((name, KUBERNETES_PROVIDER)) => ids.extend(self.resolve_service(name)),
+            Some((name, KUBERNETES_CRD_PROVIDER)) => self.resolve_crd(name, 0, &mut ids),
+            _ => debug!("Traefik service '{traefik_service}' is not provided by kubernetes"),
+        };
+        ids
+    }
+
+    /// Ids ({namespace}/{name}) of the Services routed for `host`
+    pub fn resolve_host(&self, host: &str) -> Vec<String> {
+        let mut ids = Vec::new();
+        for service in self
+            .hosts
+            .get(&host.to_ascii_lowercase())
+            .into_iter()
+            .flatten()
+        {
+            self.resolve_ref(service, 0, &mut ids);
+        }
+        ids
+    }
+
+    /// Resolve a `{namespace}-{name}-{port}` Traefik service name.
+    ///
+    /// As namespaces and names can contain '-', the longest matching Service is kept.
+    fn resolve_service(&self, name: &str) -> Option<String> {
+        self.services
+            .iter()
+            .filter(|(namespace, service)| name.starts_with(&format!("{namespace}-{service}-")))
+            .max_by_key(|(namespace, service)| namespace.len() + service.len())
+            .map(|(namespace, service)| format!("{namespace}/{service}"))
+    }
+
+    fn resolve_crd(&self, name: &str, depth: usize, ids: &mut Vec<String>) {
+        if depth > MAX_RESOLUTION_DEPTH {
+            debug!("Max resolution depth reached for Traefik service '{name}'");
+            return;
+        }
+
+        let children = if let Some(children) = self.traefik_services.get(name) {
+            children
+        } else if let Some(id) = self.resolve_service(name) {
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+            return;
+        } else if let Some((_, children)) = self
+            .ingress_routes
+            .iter()
+            .filter(|(route, _)| name.starts_with(&format!("{route}-")))
+            .max_by_key(|(route, _)| route.len())
+        {
+            children
+        } else {
+            return;
+        };
+
+        for child in children {
+            self.resolve_ref(child, depth + 1, ids);
+        }
+    }
+
+    fn resolve_ref(&self, service: &ServiceRef, depth: usize, ids: &mut Vec<String>) {
+        if service.is_traefik_service {
+            self.resolve_crd(
+                &format!("{}-{}", service.namespace, service.name),
+                depth,
+                ids,
+            );
+        } else {
+            let id = format!("{}/{}", service.namespace, service.name);
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+    }
+}
+
+/// Hosts of the `Host` matchers of an IngressRoute rule, like "Host(`a.com`) && PathPrefix(`/a`)"
+fn rule_hosts(rule: &str) -> Vec<String> {
+    let matcher = Regex::new(TRAEFIK_REGEXP_HOST).unwrap();
+    let quoted = Regex::new("`([^`]*)`").unwrap();
+    matcher
+        .captures_iter(rule)
+        .flat_map(|hosts| {
+            let (_, [hosts]) = hosts.extract();
+            quoted
+                .captures_iter(hosts)
+                .map(|host| host.extract::<1>().1[0].to_ascii_lowercase())
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// List all resources of a Traefik CRD kind, whatever the group it is served with
+async fn list_traefik_crd(client: &Client, kind: &str) -> Vec<DynamicObject> {
+    for group in TRAEFIK_CRD_GROUPS {
+        let gvk = GroupVersionKind::gvk(group, TRAEFIK_CRD_VERSION, kind);
+        let api: Api<DynamicObject> = Api::all_with(client.clone(), &ApiResource::from_gvk(&gvk));
+        match api.list(&ListParams::default()).await {
+            Ok(list) => return list.items,
+            Err(e) => debug!("Can't list '{kind}.{group}' : {e}"),
+        }
+    }
+    Vec::new()
+}
+
 impl IngressType for Traefik {
     async fn get_ingress_pods() -> Result<Vec<Pod>, IngressError> {
         let mut config = Config::infer().await?;
@@ -53,3 +315,139 @@ impl IngressType for Traefik {
         Ok(res)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::api::core::v1::Service as K8sService;
+    use serde_json::json;
+
+    fn service(namespace: &str, name: &str) -> Service {
+        let service: K8sService = serde_json::from_value(json!({
+            "metadata": { "name": name, "namespace": namespace },
+            "spec": { "ports": [{ "port": 80, "targetPort": 80 }] },
+        }))
+        .unwrap();
+        Service::try_from(&service).unwrap()
+    }
+
+    fn traefik_crd(kind: &str, namespace: &str, name: &str, spec: Value) -> DynamicObject {
+        serde_json::from_value(json!({
+            "apiVersion": "traefik.io/v1alpha1",
+            "kind": kind,
+            "metadata": { "name": name, "namespace": namespace },
+            "spec": spec,
+        }))
+        .unwrap()
+    }
+
+    fn traefik_service(namespace: &str, name: &str, children: &[(&str, &str)]) -> DynamicObject {
+        let services: Vec<Value> = children
+            .iter()
+            .map(|(kind, name)| json!({ "name": name, "kind": kind }))
+            .collect();
+        traefik_crd(
+            TRAEFIK_SERVICE_KIND,
+            namespace,
+            name,
+            json!({ "weighted": { "services": services } }),
+        )
+    }
+
+    #[test]
+    fn resolve_longest_prefix() {
+        let resolver = TraefikServiceResolver::without_crds(&[
+            service("ns", "foo"),
+            service("ns", "foo-bar"),
+            service("ns-foo", "baz"),
+        ]);
+
+        assert_eq!(resolver.resolve("ns-foo-80@kubernetes"), ["ns/foo"]);
+        assert_eq!(resolver.resolve("ns-foo-bar-80@kubernetes"), ["ns/foo-bar"]);
+        assert_eq!(
+            resolver.resolve("ns-foo-bar-80@kubernetescrd"),
+            ["ns/foo-bar"]
+        );
+        assert_eq!(resolver.resolve("ns-foo-baz-80@kubernetes"), ["ns-foo/baz"]);
+        assert!(resolver.resolve("ns-baz-80@kubernetes").is_empty());
+        assert!(resolver.resolve("ns-foo-80@file").is_empty());
+    }
+
+    #[test]
+    fn resolve_nested_traefik_service() {
+        let mut resolver =
+            TraefikServiceResolver::without_crds(&[service("ns", "blue"), service("ns", "green")]);
+        resolver.add_traefik_service(&traefik_service(
+            "ns",
+            "outer",
+            &[(TRAEFIK_SERVICE_KIND, "inner"), ("Service", "blue")],
+        ));
+        resolver.add_traefik_service(&traefik_crd(
+            TRAEFIK_SERVICE_KIND,
+            "ns",
+            "inner",
+            json!({ "mirroring": { "name": "blue", "mirrors": [{ "name": "green" }] } }),
+        ));
+
+        assert_eq!(
+            resolver.resolve("ns-outer@kubernetescrd"),
+            ["ns/blue", "ns/green"]
+        );
+        assert_eq!(
+            resolver.resolve("ns-inner@kubernetescrd"),
+            ["ns/blue", "ns/green"]
+        );
+    }
+
+    #[test]
+    fn resolve_cycle_stops_at_max_depth() {
+        let mut resolver = TraefikServiceResolver::without_crds(&[service("ns", "blue")]);
+        resolver.add_traefik_service(&traefik_service(
+            "ns",
+            "a",
+            &[(TRAEFIK_SERVICE_KIND, "b"), ("Service", "blue")],
+        ));
+        resolver.add_traefik_service(&traefik_service("ns", "b", &[(TRAEFIK_SERVICE_KIND, "a")]));
+        resolver.add_traefik_service(&traefik_service(
+            "ns",
+            "self",
+            &[(TRAEFIK_SERVICE_KIND, "self")],
+        ));
+
+        assert_eq!(resolver.resolve("ns-a@kubernetescrd"), ["ns/blue"]);
+        assert_eq!(resolver.resolve("ns-b@kubernetescrd"), ["ns/blue"]);
+        assert!(resolver.resolve("ns-self@kubernetescrd").is_empty());
+    }
+
+    #[test]
+    fn resolve_ingress_route() {
+        let mut resolver =
+            TraefikServiceResolver::without_crds(&[service("ns", "blue"), service("ns", "green")]);
+        resolver.add_ingress_route(&traefik_crd(
+            INGRESS_ROUTE_KIND,
+            "ns",
+            "web",
+            json!({ "routes": [
+                {
+                    "match": "Host(`app.example.com`) && PathPrefix(`/blue`)",
+                    "services": [{ "name": "blue", "port": 80 }],
+                },
+                {
+                    "match": "Host(`App.example.com`) || Host(`green.example.com`)",
+                    "services": [{ "name": "green", "port": 80 }],
+                },
+            ]}),
+        ));
+
+        assert_eq!(
+            resolver.resolve("ns-web-1a2b3c@kubernetescrd"),
+            ["ns/blue", "ns/green"]
+        );
+        assert_eq!(
+            resolver.resolve_host("app.example.com"),
+            ["ns/blue", "ns/green"]
+        );
+        assert_eq!(resolver.resolve_host("green.example.com"), ["ns/green"]);
+        assert!(resolver.resolve_host("other.example.com").is_empty());
+    }
+}
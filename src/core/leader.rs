@@ -0,0 +1,206 @@
+//! Leader election between kubesleeper replicas, with a Lease (coordination.k8s.io).
+//!
+//! Only the leader runs the schedule and sets resources asleep or awake. The other replicas
+//! serve the waiting page and forward the activity they receive to the leader.
+
+use k8s_openapi::{
+    api::{
+        coordination::v1::{Lease, LeaseSpec},
+        core::v1::Pod,
+    },
+    apimachinery::pkg::apis::meta::v1::{MicroTime, ObjectMeta},
+    chrono::{self, Utc},
+};
+use kube::{Api, Client, api::PostParams};
+use std::{
+    future::Future,
+    process,
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
+
+use crate::core::server::KUBESLEEPER_REST_PATH_PREFIX;
+
+/// Name of the Lease, in the namespace of kubesleeper
+pub const LEASE_NAME: &str = "kubesleeper-leader";
+
+/// Time after which a Lease which hasn't been renewed can be taken by another replica
+const LEASE_DURATION: Duration = Duration::from_secs(15);
+
+/// Time between two renewals of the Lease (or attempts to acquire it)
+const RENEW_INTERVAL: Duration = Duration::from_secs(5);
+
+static IS_LEADER: AtomicBool = AtomicBool::new(false);
+
+pub mod error {
+    #[derive(Debug, thiserror::Error)]
+    pub enum Leader {
+        #[error("KubeError : {0}")]
+        Kube(#[from] kube::Error),
+
+        #[error("ReqwestError : {0}")]
+        Reqwest(#[from] reqwest::Error),
+
+        #[error("No kubesleeper leader elected")]
+        NotElected,
+
+        #[error("Address of leader pod '{0}' not found")]
+        MissingLeaderAddress(String),
+    }
+}
+
+/// Whether this replica is the leader
+pub fn is_leader() -> bool {
+    IS_LEADER.load(Ordering::Relaxed)
+}
+
+/// Identity of this replica in the Lease : its pod name
+fn identity() -> String {
+    std::env::var("HOSTNAME").unwrap_or_else(|_| Uuid::new_v4().to_string())
+}
+
+async fn get_k8s_api() -> Result<Api<Lease>, error::Leader> {
+    let client = Client::try_default().await?;
+    Ok(Api::default_namespaced(client))
+}
+
+/// Take part in the election, running `on_elected` once this replica becomes the leader.
+///
+/// `on_elected` runs in its own task, so the Lease keeps being renewed while it runs.
+/// A leader losing the Lease exits, so it doesn't fight with the new one.
+pub async fn run<F, Fut>(on_elected: F)
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let identity = identity();
+    info!("Running leader election as '{identity}'");
+    let mut on_elected = Some(on_elected);
+    let mut last_renew = Instant::now();
+
+    loop {
+        match try_acquire(&identity).await {
+            Ok(true) => {
+                last_renew = Instant::now();
+                if let Some(on_elected) = on_elected.take() {
+                    info!("Elected leader");
+                    IS_LEADER.store(true, Ordering::Relaxed);
+                    tokio::spawn(on_elected());
+                }
+            }
+            Ok(false) if is_leader() => {
+                error!("Leadership lost : exiting");
+                process::exit(1);
+            }
+            Ok(false) => debug!("Not the leader"),
+            Err(e) if is_leader() && last_renew.elapsed() >= LEASE_DURATION => {
+                error!("Can't renew the leadership : exiting : {e}");
+                process::exit(1);
+            }
+            Err(e) => warn!("Leader election failed : {e}"),
+        }
+        tokio::time::sleep(RENEW_INTERVAL).await;
+    }
+}
+
+/// Acquire or renew the Lease for `identity`, returning if it is the holder
+async fn try_acquire(identity: &str) -> Result<bool, error::Leader> {
+    let api = get_k8s_api().await?;
+    let now = MicroTime(Utc::now());
+
+    let Some(mut lease) = api.get_opt(LEASE_NAME).await? else {
+        let lease = Lease {
+            metadata: ObjectMeta {
+                name: Some(LEASE_NAME.to_string()),
+                ..Default::default()
+            },
+            spec: Some(LeaseSpec {
+                holder_identity: Some(identity.to_string()),
+                lease_duration_seconds: Some(LEASE_DURATION.as_secs() as i32),
+                acquire_time: Some(now.clone()),
+                renew_time: Some(now),
+                lease_transitions: Some(0),
+                ..Default::default()
+            }),
+        };
+        return acquired(api.create(&PostParams::default(), &lease).await);
+    };
+
+    let spec = lease.spec.get_or_insert_default();
+    let holder = spec.holder_identity.clone();
+    if holder.as_deref() != Some(identity) {
+        let lease_duration = spec
+            .lease_duration_seconds
+            .map(|s| chrono::Duration::seconds(s.into()))
+            .unwrap_or_default();
+        let expired = spec
+            .renew_time
+            .as_ref()
+            .is_none_or(|renew_time| renew_time.0 + lease_duration < now.0);
+        if holder.is_some() && !expired {
+            return Ok(false);
+        }
+
+        info!("Taking over the expired Lease of {holder:?}");
+        spec.holder_identity = Some(identity.to_string());
+        spec.acquire_time = Some(now.clone());
+        spec.lease_transitions = Some(spec.lease_transitions.unwrap_or_default() + 1);
+    }
+    spec.lease_duration_seconds = Some(LEASE_DURATION.as_secs() as i32);
+    spec.renew_time = Some(now);
+
+    // the resource version of `lease` makes the replacement fail if another replica updated it
+    acquired(
+        api.replace(LEASE_NAME, &PostParams::default(), &lease)
+            .await,
+    )
+}
+
+fn acquired(result: Result<Lease, kube::Error>) -> Result<bool, error::Leader> {
+    match result {
+        Ok(_) => Ok(true),
+        // another replica updated the Lease first
+        Err(kube::Error::Api(e)) if e.code == 409 => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Address of the leader pod
+async fn leader_address() -> Result<String, error::Leader> {
+    let holder = get_k8s_api()
+        .await?
+        .get_opt(LEASE_NAME)
+        .await?
+        .and_then(|lease| lease.spec)
+        .and_then(|spec| spec.holder_identity)
+        .ok_or(error::Leader::NotElected)?;
+
+    let client = Client::try_default().await?;
+    Api::<Pod>::default_namespaced(client)
+        .get_opt(&holder)
+        .await?
+        .and_then(|pod| pod.status)
+        .and_then(|status| status.pod_ip)
+        .ok_or(error::Leader::MissingLeaderAddress(holder))
+}
+
+/// Forward a request received for `host` to the leader, listening on `port`
+pub async fn forward_activity(host: Option<&str>, port: u16) -> Result<(), error::Leader> {
+    let address = leader_address().await?;
+    let ip = match address.contains(':') {
+        true => format!("[{address}]"),
+        false => address,
+    };
+    debug!("Forwarding activity of host {host:?} to the leader ({ip})");
+
+    let url = format!("http://{ip}:{port}{KUBESLEEPER_REST_PATH_PREFIX}/activity");
+    reqwest::Client::new()
+        .post(url)
+        .query(&[("host", host)])
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
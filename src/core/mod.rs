@@ -1,6 +1,8 @@
 pub mod config;
 pub mod ingress;
+pub mod leader;
 pub mod logger;
 pub mod resource;
 pub mod server;
 pub mod state;
+pub mod swappable;
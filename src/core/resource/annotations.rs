@@ -1,6 +1,10 @@
-use std::collections::{BTreeMap, HashMap};
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::Duration,
+};
+use tracing::warn;
 
-use crate::core::resource::constantes::*;
+use crate::core::{config::duration, resource::constantes::*};
 
 #[derive(Debug, Default)]
 pub struct Annotations(HashMap<String, String>);
@@ -11,6 +15,19 @@ impl Annotations {
             .get(&(KUBESLEEPER_ANNOTATION_PREFIX.to_string() + key))
             .map(String::as_str)
     }
+
+    /// Value of the `kubesleeper/sleepiness-duration` annotation of the resource `id`,
+    /// ignored if invalid
+    pub fn sleepiness_duration(&self, id: &str) -> Option<Duration> {
+        let raw = self.get(ANNOTATION_SLEEPINESS_DURATION_KEY)?;
+        duration::parse(raw)
+            .inspect_err(|err| {
+                warn!(
+                    "Ignoring annotation '{KUBESLEEPER_ANNOTATION_PREFIX}{ANNOTATION_SLEEPINESS_DURATION_KEY}: {raw}' of '{id}' : {err}"
+                )
+            })
+            .ok()
+    }
 }
 
 impl From<&BTreeMap<String, String>> for Annotations {
@@ -0,0 +1,261 @@
+use k8s_openapi::api::batch::v1::CronJob as K8sCronJob;
+use kube::{
+    Api, Client, ResourceExt,
+    api::{ListParams, Patch, PatchParams},
+    runtime::reflector::Lookup,
+};
+use serde::Serialize;
+use std::{collections::BTreeMap, fmt, time::Duration};
+use tracing::{debug, warn};
+
+use crate::core::{
+    resource::{
+        TargetResource, annotations::Annotations, constantes::*, error, management::is_managed,
+        namespaces::is_watched,
+    },
+    state::state_kind::StateKind,
+};
+
+#[derive(Serialize)]
+pub struct CronJob {
+    pub id: String,
+    pub name: String,
+    pub namespace: String,
+    pub suspend: bool,
+    pub group: Option<String>,
+    pub sleepiness_duration: Option<Duration>,
+
+    /// `suspend` value before the CronJob was set asleep
+    pub store_suspend: bool,
+}
+
+impl TryFrom<&K8sCronJob> for CronJob {
+    type Error = error::Resource;
+
+    fn try_from(cronjob: &K8sCronJob) -> std::result::Result<Self, Self::Error> {
+        // --- explicit data
+
+        let name = cronjob
+            .name()
+            .ok_or(error::ResourceParse::MissingValue {
+                id: "?/?".to_string(),
+                value: "name".to_string(),
+            })?
+            .to_string();
+
+        let namespace =
+            ResourceExt::namespace(cronjob).ok_or(error::ResourceParse::MissingValue {
+                id: name.clone(),
+                value: "namespace".to_string(),
+            })?;
+
+        let id = format!("{namespace}/{name}");
+
+        let suspend = cronjob
+            .spec
+            .as_ref()
+            .ok_or(error::ResourceParse::MissingValue {
+                id: id.clone(),
+                value: ".spec".to_string(),
+            })?
+            .suspend
+            .unwrap_or(false);
+
+        // --- store annotation
+        let raw_annotations = cronjob.metadata.annotations.as_ref();
+        let annotations = Annotations::from(raw_annotations.unwrap_or(&BTreeMap::default()));
+
+        // The store annotation is removed on wake : a suspended CronJob without it
+        // has been suspended by someone else and must stay suspended
+        let store_suspend = match (suspend, annotations.get(ANNOTATION_STORE_SUSPEND_KEY)) {
+            (true, Some(raw_store_suspend)) => raw_store_suspend.parse::<bool>().map_err(
+                |err| error::ResourceParse::ParseFailed {
+                    id: id.clone(),
+                    value: format!(
+                        ".annotations.{KUBESLEEPER_ANNOTATION_PREFIX}{ANNOTATION_STORE_SUSPEND_KEY}"
+                    ),
+                    error: format!("{err}"),
+                },
+            )?,
+            (suspend, _) => suspend,
+        };
+
+        let group = annotations.get(ANNOTATION_GROUP_KEY).map(str::to_string);
+        let sleepiness_duration = annotations.sleepiness_duration(&id);
+
+        Ok(CronJob {
+            id,
+            name,
+            namespace,
+            suspend,
+            group,
+            sleepiness_duration,
+            store_suspend,
+        })
+    }
+}
+
+impl CronJob {
+    pub async fn get_k8s_api(namespace: Option<&str>) -> Result<Api<K8sCronJob>, error::Resource> {
+        let client = Client::try_default().await?;
+
+        let cronjobs: Api<K8sCronJob> = if let Some(namespace_name) = namespace {
+            Api::namespaced(client, namespace_name)
+        } else {
+            Api::all(client)
+        };
+
+        Ok(cronjobs)
+    }
+}
+
+impl TargetResource<'static> for CronJob {
+    type K8sResource = K8sCronJob;
+
+    fn is_asleep(&self) -> bool {
+        self.suspend
+    }
+
+    async fn wake(&mut self) -> Result<(), error::Resource> {
+        // skip if resource as already a 'awake' stored state
+        if !self.is_asleep() {
+            debug!(
+                "State of cronjob '{}' already marked as '{}', skipping wake action",
+                self.id,
+                StateKind::Awake
+            );
+            return Ok(());
+        }
+
+        // edit resource to set it in a awake state
+        self.suspend = self.store_suspend;
+
+        // patch related k8s resource
+        self.patch().await
+    }
+
+    async fn sleep(&mut self) -> Result<(), error::Resource> {
+        // skip if resource as already a 'asleep' stored state
+        if self.is_asleep() {
+            debug!(
+                "State of cronjob '{}' already marked as '{}', skipping sleep action",
+                self.id,
+                StateKind::Asleep
+            );
+            return Ok(());
+        }
+
+        // edit resource to set it in a asleep state
+        self.store_suspend = self.suspend;
+        self.suspend = true;
+
+        // patch related k8s resource
+        self.patch().await
+    }
+
+    async fn list_k8s_resources() -> Result<Vec<Self::K8sResource>, error::Resource> {
+        let lp = super::label_selector::list_params();
+
+        Ok(Self::get_k8s_api(None)
+            .await?
+            .list(&lp)
+            .await?
+            .items
+            .into_iter()
+            .filter(is_watched)
+            .collect())
+    }
+
+    /// Objects which can't be parsed are skipped, so they don't prevent the others from being
+    /// managed
+    async fn get_all() -> Result<Vec<Self>, error::Resource> {
+        Ok(Self::list_k8s_resources()
+            .await?
+            .iter()
+            .filter(|c| is_managed(*c))
+            .filter_map(|c| {
+                Self::try_from(c)
+                    .inspect_err(|e| warn!("Skipping CronJob : {e}"))
+                    .ok()
+            })
+            .collect())
+    }
+
+    async fn patch(&self) -> Result<(), error::Resource> {
+        // the store annotation is only kept while the CronJob is asleep
+        let store_suspend = match self.suspend {
+            true => serde_json::Value::String(self.store_suspend.to_string()),
+            false => serde_json::Value::Null,
+        };
+
+        let patch = serde_json::json!({
+            "spec" : {
+                "suspend": self.suspend
+            },
+            "metadata": {
+                "annotations": {
+                    format!("{KUBESLEEPER_ANNOTATION_PREFIX}{ANNOTATION_STORE_SUSPEND_KEY}"): store_suspend
+                }
+            }
+        });
+        if super::dry_run::skip_patch("CronJob", &self.id, &patch) {
+            return Ok(());
+        }
+
+        let params = PatchParams::default();
+        let patch = Patch::Merge(&patch);
+        Self::get_k8s_api(Some(&self.namespace))
+            .await?
+            .patch(&self.name, &params, &patch)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_k8s_resource(&self) -> Result<Self::K8sResource, error::Resource> {
+        let lp = ListParams::default()
+            .match_any()
+            .fields(&format!("metadata.name={}", self.name));
+
+        Self::get_k8s_api(Some(&self.namespace))
+            .await?
+            .list(&lp)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or(error::Resource::K8sResourceNotFound {
+                id: self.id.clone(),
+            })
+    }
+
+    fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    fn group_annotation(&self) -> Option<&str> {
+        self.group.as_deref()
+    }
+
+    fn sleepiness_duration(&self) -> Option<Duration> {
+        self.sleepiness_duration
+    }
+}
+
+impl fmt::Display for CronJob {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{}",
+            serde_yaml::to_string(&self)
+                .unwrap_or_else(|e| format!(
+                    "{e} : The structure should always be serializable at this point"
+                ))
+                .trim()
+        )?;
+        Ok(())
+    }
+}
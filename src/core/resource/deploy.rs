@@ -5,7 +5,7 @@ use kube::{
     runtime::reflector::Lookup,
 };
 use serde::Serialize;
-use tracing::debug;
+use tracing::{debug, warn};
 
 use crate::core::{
     resource::{constantes::*, error},
@@ -13,7 +13,6 @@ use crate::core::{
 };
 use std::time::Duration;
 use std::{collections::BTreeMap, fmt};
-use tracing::log::info;
 
 #[derive(Serialize)]
 pub struct Deploy {
@@ -21,6 +20,8 @@ pub struct Deploy {
     pub name: String,
     pub namespace: String,
     pub replicas: i32,
+    pub group: Option<String>,
+    pub sleepiness_duration: Option<Duration>,
 
     pub store_replicas: i32,
 }
@@ -58,43 +59,37 @@ impl TryFrom<&Deployment> for Deploy {
         let annotations =
             super::annotations::Annotations::from(raw_annotations.unwrap_or(&BTreeMap::default()));
 
-        // replicas
-        let store_replicas = if replicas == 0 {
-            annotations
-                .get(ANNOTATION_STORE_REPLICAS_KEY)
-                .map(|raw_store_replicas| {
-                    raw_store_replicas.parse::<i32>().map_err(|err| {
-                        error::ResourceParse::ParseFailed {
-                            id: format!("{id}"),
-                            value: format!(
-                                ".annotations.{}{}",
-                                KUBESLEEPER_ANNOTATION_PREFIX, ANNOTATION_STORE_REPLICAS_KEY
-                            ),
-                            error: format!("{err}"),
-                        }
-                    })
-                })
-                .unwrap_or(Err(error::ResourceParse::MissingAnnotationInSleepState {
-                    id: format!("{id}"),
-                    annotation: format!(
-                        "{}{}",
-                        KUBESLEEPER_ANNOTATION_PREFIX, ANNOTATION_STORE_REPLICAS_KEY
-                    ),
-                }))?
-        } else {
-            replicas
-        };
+        let store_replicas = store_replicas(&id, replicas, &annotations)?;
+
+        let group = annotations.get(ANNOTATION_GROUP_KEY).map(str::to_string);
+        let sleepiness_duration = annotations.sleepiness_duration(&id);
 
         Ok(Deploy {
             id,
             name,
             namespace,
             replicas,
+            group,
+            sleepiness_duration,
             store_replicas,
         })
     }
 }
 
+impl Deploy {
+    pub async fn get_k8s_api(namespace: Option<&str>) -> Result<Api<Deployment>, error::Resource> {
+        let client = Client::try_default().await?;
+
+        let deployments: Api<Deployment> = if let Some(namespace_name) = namespace {
+            Api::namespaced(client, namespace_name)
+        } else {
+            Api::all(client)
+        };
+
+        return Ok(deployments);
+    }
+}
+
 impl super::TargetResource<'static> for Deploy {
     type K8sResource = Deployment;
 
@@ -142,33 +137,33 @@ impl super::TargetResource<'static> for Deploy {
         self.patch().await
     }
 
-    async fn get_k8s_api(
-        namespace: Option<&str>,
-    ) -> Result<Api<Self::K8sResource>, error::Resource> {
-        let client = Client::try_default().await?;
-
-        let deployments: Api<Self::K8sResource> = if let Some(namespace_name) = namespace {
-            Api::namespaced(client, namespace_name)
-        } else {
-            Api::all(client)
-        };
+    async fn list_k8s_resources() -> Result<Vec<Self::K8sResource>, error::Resource> {
+        let lp = super::label_selector::list_params()
+            .fields(&format!("metadata.name!={KUBESLLEPER_APP_NAME}"));
 
-        return Ok(deployments);
+        Ok(Self::get_k8s_api(None)
+            .await?
+            .list(&lp)
+            .await?
+            .items
+            .into_iter()
+            .filter(super::namespaces::is_watched)
+            .collect())
     }
 
+    /// Objects which can't be parsed (like a Deployment scaled to 0 by hand) are skipped, so
+    /// they don't prevent the others from being managed
     async fn get_all() -> Result<Vec<Self>, error::Resource> {
-        let lp = ListParams::default().match_any().fields(&format!(
-            "metadata.name!={},metadata.namespace!=kube-system",
-            KUBESLLEPER_APP_NAME
-        ));
-
-        Self::get_k8s_api(None)
-            .await?
-            .list(&lp)
+        Ok(Self::list_k8s_resources()
             .await?
             .iter()
-            .map(|d| Self::try_from(d))
-            .collect()
+            .filter(|d| super::management::is_managed(*d))
+            .filter_map(|d| {
+                Self::try_from(d)
+                    .inspect_err(|e| warn!("Skipping Deployment : {e}"))
+                    .ok()
+            })
+            .collect())
     }
 
     async fn patch(&self) -> Result<(), error::Resource> {
@@ -182,6 +177,10 @@ impl super::TargetResource<'static> for Deploy {
                 }
             }
         });
+        if super::dry_run::skip_patch("Deployment", &self.id, &patch) {
+            return Ok(());
+        }
+
         let params = PatchParams::default();
         let patch = Patch::Merge(&patch);
         Self::get_k8s_api(Some(&self.namespace))
@@ -211,12 +210,42 @@ impl super::TargetResource<'static> for Deploy {
     fn id(&self) -> String {
         return self.id.clone();
     }
+
+    fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    fn group_annotation(&self) -> Option<&str> {
+        self.group.as_deref()
+    }
+
+    fn sleepiness_duration(&self) -> Option<Duration> {
+        self.sleepiness_duration
+    }
 }
 
-use super::TargetResource;
+use super::{
+    TargetResource,
+    hpa::ScaleTarget,
+    replicas::{Replicated, store_replicas},
+};
 
 impl Deploy {
-    pub async fn get_ready_replicas_count(&self) -> Result<i32, error::Resource> {
+    pub fn scale_target(&self) -> ScaleTarget<'_> {
+        (&self.namespace, "Deployment", &self.name)
+    }
+}
+
+impl Replicated for Deploy {
+    fn kind(&self) -> &str {
+        "Deployment"
+    }
+
+    fn replicas(&self) -> i32 {
+        self.replicas
+    }
+
+    async fn get_ready_replicas_count(&self) -> Result<i32, error::Resource> {
         Ok(self
             .get_k8s_resource()
             .await?
@@ -228,31 +257,6 @@ impl Deploy {
             .ready_replicas
             .unwrap_or_default())
     }
-
-    pub async fn wait_ready(&self) -> Result<(), error::Resource> {
-        let mut total_duration = 0;
-        for i in 0_u32..1000 {
-            let current_ready_replicas = self.get_ready_replicas_count().await?;
-            if self.replicas - self.get_ready_replicas_count().await? == 0 {
-                info!("Deploy {} just woke up.", self.id);
-                return Ok(());
-            }
-
-            info!(
-                "Deploy {} is waking up. Waiting for replicas to be ready : {}/{}",
-                self.id, current_ready_replicas, self.replicas
-            );
-
-            let duration = 100 * 2_u64.pow([i, 7].into_iter().min().expect("Couldn't be empty"));
-            tokio::time::sleep(Duration::from_millis(duration)).await;
-            total_duration += duration;
-        }
-
-        Err(error::Resource::MaxWaitingWakeTime {
-            id: format!("{}", self.id),
-            max_waiting_time: total_duration,
-        })
-    }
 }
 
 impl fmt::Display for Deploy {
@@ -0,0 +1,23 @@
+//! Dry run mode : resources aren't changed, the patches kubesleeper would send are logged instead.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::info;
+
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+
+pub fn enable() {
+    info!("Dry run : resources won't be changed");
+    DRY_RUN.store(true, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    DRY_RUN.load(Ordering::Relaxed)
+}
+
+/// Log `patch` of the resource `kind` `id` in dry run mode, returning if it must not be sent
+pub fn skip_patch(kind: &str, id: &str, patch: &serde_json::Value) -> bool {
+    if is_enabled() {
+        info!("Dry run : {kind} '{id}' not patched : {patch}");
+    }
+    is_enabled()
+}
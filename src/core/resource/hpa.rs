@@ -0,0 +1,291 @@
+use k8s_openapi::api::autoscaling::v2::HorizontalPodAutoscaler;
+use kube::{
+    Api, Client, ResourceExt,
+    api::{ListParams, Patch, PatchParams},
+    runtime::reflector::Lookup,
+};
+use serde::Serialize;
+use std::{collections::BTreeMap, fmt};
+use tracing::debug;
+
+use crate::core::{
+    resource::{
+        TargetResource, annotations::Annotations, constantes::*, error, namespaces::is_watched,
+    },
+    state::state_kind::StateKind,
+};
+
+/// Scaling policy disabling scaling in a direction
+const DISABLED_SELECT_POLICY: &str = "Disabled";
+
+/// A resource which can be scaled by an HPA, as `(namespace, kind, name)`
+pub type ScaleTarget<'a> = (&'a str, &'a str, &'a str);
+
+/// A HorizontalPodAutoscaler, whose scale up is disabled while its target is asleep
+/// so it doesn't scale it back up
+#[derive(Serialize)]
+pub struct Hpa {
+    pub id: String,
+    pub name: String,
+    pub namespace: String,
+
+    /// Kind of the scaled resource (like 'Deployment')
+    pub target_kind: String,
+    /// Name of the scaled resource, in the namespace of the HPA
+    pub target_name: String,
+
+    /// `.spec.behavior.scaleUp.selectPolicy`
+    pub scale_up_policy: Option<String>,
+    pub group: Option<String>,
+
+    pub store_scale_up_policy: Option<String>,
+}
+
+impl TryFrom<&HorizontalPodAutoscaler> for Hpa {
+    type Error = error::Resource;
+
+    fn try_from(hpa: &HorizontalPodAutoscaler) -> std::result::Result<Self, Self::Error> {
+        // --- explicit data
+
+        let name = hpa
+            .name()
+            .ok_or(error::ResourceParse::MissingValue {
+                id: "?/?".to_string(),
+                value: "name".to_string(),
+            })?
+            .to_string();
+
+        let namespace = ResourceExt::namespace(hpa).ok_or(error::ResourceParse::MissingValue {
+            id: name.clone(),
+            value: "namespace".to_string(),
+        })?;
+
+        let id = format!("{namespace}/{name}");
+
+        let spec = hpa
+            .spec
+            .as_ref()
+            .ok_or(error::ResourceParse::MissingValue {
+                id: id.clone(),
+                value: ".spec".to_string(),
+            })?;
+
+        let scale_up_policy = spec
+            .behavior
+            .as_ref()
+            .and_then(|b| b.scale_up.as_ref())
+            .and_then(|s| s.select_policy.clone());
+
+        // --- store annotation
+        let raw_annotations = hpa.metadata.annotations.as_ref();
+        let annotations = Annotations::from(raw_annotations.unwrap_or(&BTreeMap::default()));
+
+        // The store annotation is removed on wake : a disabled scale up without it
+        // has been disabled by someone else and must stay disabled.
+        // An empty stored value means there was no policy set.
+        let store_scale_up_policy = match annotations.get(ANNOTATION_STORE_SCALE_UP_POLICY_KEY) {
+            Some(stored) if scale_up_policy.as_deref() == Some(DISABLED_SELECT_POLICY) => {
+                Some(stored.to_string()).filter(|s| !s.is_empty())
+            }
+            _ => scale_up_policy.clone(),
+        };
+
+        let group = annotations.get(ANNOTATION_GROUP_KEY).map(str::to_string);
+
+        Ok(Hpa {
+            id,
+            name,
+            namespace,
+            target_kind: spec.scale_target_ref.kind.clone(),
+            target_name: spec.scale_target_ref.name.clone(),
+            scale_up_policy,
+            group,
+            store_scale_up_policy,
+        })
+    }
+}
+
+impl Hpa {
+    pub async fn get_k8s_api(
+        namespace: Option<&str>,
+    ) -> Result<Api<HorizontalPodAutoscaler>, error::Resource> {
+        let client = Client::try_default().await?;
+
+        let hpas: Api<HorizontalPodAutoscaler> = if let Some(namespace_name) = namespace {
+            Api::namespaced(client, namespace_name)
+        } else {
+            Api::all(client)
+        };
+
+        Ok(hpas)
+    }
+
+    /// Check if the HPA scales `target`
+    pub fn scales(&self, (namespace, kind, name): ScaleTarget<'_>) -> bool {
+        self.namespace == namespace && self.target_kind == kind && self.target_name == name
+    }
+
+    /// Get all HPAs scaling one of `targets`, HPAs of unmanaged resources are left untouched
+    pub async fn get_all_scaling(
+        targets: &[ScaleTarget<'_>],
+    ) -> Result<Vec<Self>, error::Resource> {
+        let mut hpas = Self::get_all().await?;
+        hpas.retain(|hpa| targets.iter().any(|target| hpa.scales(*target)));
+        Ok(hpas)
+    }
+}
+
+impl TargetResource<'static> for Hpa {
+    type K8sResource = HorizontalPodAutoscaler;
+
+    fn is_asleep(&self) -> bool {
+        self.scale_up_policy.as_deref() == Some(DISABLED_SELECT_POLICY)
+    }
+
+    async fn wake(&mut self) -> Result<(), error::Resource> {
+        // skip if resource as already a 'awake' stored state
+        if !self.is_asleep() {
+            debug!(
+                "State of hpa '{}' already marked as '{}', skipping wake action",
+                self.id,
+                StateKind::Awake
+            );
+            return Ok(());
+        }
+
+        // edit resource to set it in a awake state
+        self.scale_up_policy = self.store_scale_up_policy.clone();
+
+        // patch related k8s resource
+        self.patch().await
+    }
+
+    async fn sleep(&mut self) -> Result<(), error::Resource> {
+        // skip if resource as already a 'asleep' stored state
+        if self.is_asleep() {
+            debug!(
+                "State of hpa '{}' already marked as '{}', skipping sleep action",
+                self.id,
+                StateKind::Asleep
+            );
+            return Ok(());
+        }
+
+        // edit resource to set it in a asleep state
+        self.store_scale_up_policy = self.scale_up_policy.clone();
+        self.scale_up_policy = Some(DISABLED_SELECT_POLICY.to_string());
+
+        // patch related k8s resource
+        self.patch().await
+    }
+
+    async fn list_k8s_resources() -> Result<Vec<Self::K8sResource>, error::Resource> {
+        // not restricted by the label selector : autoscalers follow their workload
+        let lp = ListParams::default().match_any();
+
+        Ok(Self::get_k8s_api(None)
+            .await?
+            .list(&lp)
+            .await?
+            .items
+            .into_iter()
+            .filter(is_watched)
+            .collect())
+    }
+
+    async fn get_all() -> Result<Vec<Self>, error::Resource> {
+        Self::list_k8s_resources()
+            .await?
+            .iter()
+            // HPAs created by KEDA are handled through their ScaledObject
+            .filter(|hpa| {
+                !hpa.owner_references()
+                    .iter()
+                    .any(|owner| owner.kind == "ScaledObject")
+            })
+            // not filtered by their annotation : autoscalers follow the management of their
+            // workload (see `get_all_scaling`)
+            .map(Self::try_from)
+            .collect()
+    }
+
+    async fn patch(&self) -> Result<(), error::Resource> {
+        // the store annotation is only kept while the HPA is asleep
+        let store_scale_up_policy = match self.is_asleep() {
+            true => {
+                serde_json::Value::String(self.store_scale_up_policy.clone().unwrap_or_default())
+            }
+            false => serde_json::Value::Null,
+        };
+
+        let patch = serde_json::json!({
+            "spec" : {
+                "behavior": {
+                    "scaleUp": {
+                        "selectPolicy": self.scale_up_policy
+                    }
+                }
+            },
+            "metadata": {
+                "annotations": {
+                    format!("{KUBESLEEPER_ANNOTATION_PREFIX}{ANNOTATION_STORE_SCALE_UP_POLICY_KEY}"): store_scale_up_policy
+                }
+            }
+        });
+        if super::dry_run::skip_patch("HorizontalPodAutoscaler", &self.id, &patch) {
+            return Ok(());
+        }
+
+        let params = PatchParams::default();
+        let patch = Patch::Merge(&patch);
+        Self::get_k8s_api(Some(&self.namespace))
+            .await?
+            .patch(&self.name, &params, &patch)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_k8s_resource(&self) -> Result<Self::K8sResource, error::Resource> {
+        let lp = ListParams::default()
+            .match_any()
+            .fields(&format!("metadata.name={}", self.name));
+
+        Self::get_k8s_api(Some(&self.namespace))
+            .await?
+            .list(&lp)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or(error::Resource::K8sResourceNotFound {
+                id: self.id.clone(),
+            })
+    }
+
+    fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    fn group_annotation(&self) -> Option<&str> {
+        self.group.as_deref()
+    }
+}
+
+impl fmt::Display for Hpa {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{}",
+            serde_yaml::to_string(&self)
+                .unwrap_or_else(|e| format!(
+                    "{e} : The structure should always be serializable at this point"
+                ))
+                .trim()
+        )?;
+        Ok(())
+    }
+}
@@ -0,0 +1,15 @@
+use kube::api::ListParams;
+
+use crate::core::swappable::Swappable;
+
+pub static LABEL_SELECTOR: Swappable<Option<String>> = Swappable::new();
+
+/// Parameters to list the workloads kubesleeper may manage, restricted to the
+/// configured label selector (like 'env in (dev,preview)') if any
+pub fn list_params() -> ListParams {
+    let lp = ListParams::default().match_any();
+    match LABEL_SELECTOR.get().flatten() {
+        Some(selector) => lp.labels(&selector),
+        None => lp,
+    }
+}
@@ -0,0 +1,46 @@
+use kube::{Resource, ResourceExt};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::core::{
+    resource::{annotations::Annotations, constantes::*},
+    swappable::Swappable,
+};
+
+pub static MANAGEMENT: Swappable<Management> = Swappable::new();
+
+/// Which resources are managed by kubesleeper, by default
+#[derive(Eq, PartialEq, Debug, Serialize, Deserialize, Clone, Copy, Default, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Management {
+    /// All resources are managed, unless annotated with `kubesleeper/enabled: "false"`
+    #[default]
+    OptOut,
+
+    /// Only resources annotated with `kubesleeper/enabled: "true"` are managed
+    OptIn,
+}
+
+pub fn management() -> Management {
+    MANAGEMENT.get().unwrap_or_default()
+}
+
+/// Check if a k8s resource is managed by kubesleeper, from its `kubesleeper/enabled`
+/// annotation or from the management mode if it isn't set
+pub fn is_managed<K: Resource>(resource: &K) -> bool {
+    let annotations = Annotations::from(resource.annotations());
+
+    let enabled = annotations.get(ANNOTATION_ENABLED_KEY).and_then(|raw| {
+        raw.parse::<bool>()
+            .inspect_err(|err| {
+                warn!(
+                    "Ignoring annotation '{KUBESLEEPER_ANNOTATION_PREFIX}{ANNOTATION_ENABLED_KEY}: {raw}' of '{}' : {err}",
+                    resource.name_any()
+                )
+            })
+            .ok()
+    });
+
+    enabled.unwrap_or(management() == Management::OptOut)
+}
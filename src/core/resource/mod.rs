@@ -1,21 +1,38 @@
-use kube::Api;
+use crate::core::state::group::GroupBy;
 
 pub mod annotations;
+pub mod cron_job;
 pub mod deploy;
+pub mod dry_run;
+pub mod hpa;
+pub mod label_selector;
+pub mod management;
+pub mod namespaces;
+pub mod redirection;
+pub mod replicas;
+pub mod scalable;
+pub mod scaled_object;
 pub mod service;
+pub mod stateful_set;
 
 #[rustfmt::skip]
 pub mod constantes{
-    pub const KUBESLEEPER_ANNOTATION_PREFIX     : &str = "kubesleeper/";
-    pub const ANNOTATION_STORE_REPLICAS_KEY     : &str = "store.replicas";
-    pub const ANNOTATION_STORE_SELECTOR_KEY     : &str = "store.selectors";
-    pub const ANNOTATION_STORE_PORTS_KEY        : &str = "store.ports";
-    pub const KUBESLLEPER_APP_NAME              : &str = "kubesleeper";
+    pub const KUBESLEEPER_ANNOTATION_PREFIX        : &str = "kubesleeper/";
+    pub const ANNOTATION_STORE_REPLICAS_KEY        : &str = "store.replicas";
+    pub const ANNOTATION_STORE_SELECTOR_KEY        : &str = "store.selectors";
+    pub const ANNOTATION_STORE_PORTS_KEY           : &str = "store.ports";
+    pub const ANNOTATION_STORE_SUSPEND_KEY         : &str = "store.suspend";
+    pub const ANNOTATION_STORE_SCALE_UP_POLICY_KEY : &str = "store.scale-up-policy";
+    pub const ANNOTATION_STORE_PAUSED_REPLICAS_KEY : &str = "store.paused-replicas";
+    pub const ANNOTATION_GROUP_KEY                 : &str = "group";
+    pub const ANNOTATION_ENABLED_KEY               : &str = "enabled";
+    pub const ANNOTATION_SLEEPINESS_DURATION_KEY   : &str = "sleepiness-duration";
+    pub const KUBESLLEPER_APP_NAME                 : &str = "kubesleeper";
 
     pub const KUBESLEEPER_SELECTOR_KEY   : &str = "app";
     pub const KUBESLEEPER_SELECTOR_VALUE : &str = "kubesleeper";
     
-    pub const KUBESLEEPER_SERVER_PORT           : i32 = 8000;
+    pub const KUBESLEEPER_SERVER_PORT              : i32 = 8000;
 }
 
 pub mod error {
@@ -49,7 +66,7 @@ pub mod error {
         TooMuchKubesleeperDeploy(usize),
 
         #[allow(dead_code)]
-        #[error("Max waiting time exceeded ({max_waiting_time}s) for deployment {id}")]
+        #[error("Max waiting time exceeded ({max_waiting_time}s) for resource {id}")]
         MaxWaitingWakeTime { id: String, max_waiting_time: u64 },
     }
 
@@ -85,15 +102,30 @@ pub mod error {
     }
 }
 
-pub trait TargetResource<'a>:
-    std::fmt::Display + TryFrom<&'a Self::K8sResource, Error = error::Resource>
-{
-    type K8sResource: 'a;
+pub trait TargetResource<'a>: std::fmt::Display + Sized {
+    type K8sResource: kube::Resource + 'a;
 
+    /// List the k8s resources of this type, managed or not
+    async fn list_k8s_resources() -> Result<Vec<Self::K8sResource>, error::Resource>;
+
+    /// Get all resources managed by kubesleeper
     async fn get_all() -> Result<Vec<Self>, error::Resource>;
-    async fn get_k8s_api(
-        namespace: Option<&str>,
-    ) -> Result<Api<Self::K8sResource>, error::Resource>;
+
+    /// Ids of the resources excluded from kubesleeper management
+    async fn get_unmanaged_ids() -> Result<Vec<String>, error::Resource> {
+        Ok(Self::list_k8s_resources()
+            .await?
+            .iter()
+            .filter(|r| !management::is_managed(*r))
+            .map(|r| {
+                format!(
+                    "{}/{}",
+                    kube::ResourceExt::namespace(r).unwrap_or_default(),
+                    kube::ResourceExt::name_any(r)
+                )
+            })
+            .collect())
+    }
 
     async fn wake(&mut self) -> Result<(), error::Resource>; // uses patch
     async fn sleep(&mut self) -> Result<(), error::Resource>; // uses patch
@@ -101,4 +133,18 @@ pub trait TargetResource<'a>:
     fn is_asleep(&self) -> bool;
     async fn get_k8s_resource(&self) -> Result<Self::K8sResource, error::Resource>;
     fn id(&self) -> String;
+    fn namespace(&self) -> &str;
+
+    /// Value of the `kubesleeper/group` annotation, if any
+    fn group_annotation(&self) -> Option<&str>;
+
+    /// Value of the `kubesleeper/sleepiness-duration` annotation, if any
+    fn sleepiness_duration(&self) -> Option<std::time::Duration> {
+        None
+    }
+
+    /// Name of the group the resource belongs to
+    fn group(&self, group_by: &GroupBy) -> String {
+        group_by.group_of(self.namespace(), self.group_annotation())
+    }
 }
@@ -0,0 +1,88 @@
+use glob::Pattern;
+use kube::{Resource, ResourceExt};
+use schemars::{JsonSchema, Schema, SchemaGenerator, json_schema};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Cow;
+
+use crate::core::swappable::Swappable;
+
+pub static NAMESPACES: Swappable<Namespaces> = Swappable::new();
+
+/// A glob pattern matching namespace names (like 'team-*')
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct NamespacePattern(Pattern);
+
+impl NamespacePattern {
+    pub fn matches(&self, namespace: &str) -> bool {
+        self.0.matches(namespace)
+    }
+}
+
+impl Serialize for NamespacePattern {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.0.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for NamespacePattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = String::deserialize(deserializer)?;
+        Pattern::new(&raw).map(NamespacePattern).map_err(|e| {
+            serde::de::Error::custom(format!("invalid namespace pattern '{raw}' : {e}"))
+        })
+    }
+}
+
+impl JsonSchema for NamespacePattern {
+    fn schema_name() -> Cow<'static, str> {
+        "NamespacePattern".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "string",
+            "description": "A glob pattern matching namespace names (like 'team-*')"
+        })
+    }
+}
+
+/// Namespaces whose resources are watched by kubesleeper
+#[derive(Serialize, Debug, Deserialize, Clone, Eq, PartialEq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Namespaces {
+    /// Only namespaces matching one of these patterns are watched, all of them if empty
+    #[serde(default)]
+    pub include: Vec<NamespacePattern>,
+
+    /// Namespaces matching one of these patterns are never watched, even if included
+    #[serde(default = "default_exclude")]
+    pub exclude: Vec<NamespacePattern>,
+}
+
+fn default_exclude() -> Vec<NamespacePattern> {
+    vec![NamespacePattern(
+        Pattern::new("kube-system").expect("Should be a valid pattern"),
+    )]
+}
+
+impl Default for Namespaces {
+    fn default() -> Self {
+        Namespaces {
+            include: Vec::new(),
+            exclude: default_exclude(),
+        }
+    }
+}
+
+impl Namespaces {
+    pub fn is_watched(&self, namespace: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|p| p.matches(namespace)))
+            && !self.exclude.iter().any(|p| p.matches(namespace))
+    }
+}
+
+/// Check if a k8s resource lives in a namespace watched by kubesleeper
+pub fn is_watched<K: Resource>(resource: &K) -> bool {
+    let namespace = resource.namespace().unwrap_or_default();
+    NAMESPACES.get().unwrap_or_default().is_watched(&namespace)
+}
@@ -0,0 +1,229 @@
+use k8s_openapi::{
+    api::{
+        core::v1::Pod,
+        discovery::v1::{Endpoint, EndpointConditions, EndpointPort, EndpointSlice},
+    },
+    apimachinery::pkg::apis::meta::v1::ObjectMeta,
+};
+use kube::{
+    Api, Client, ResourceExt,
+    api::{DeleteParams, ListParams, Patch, PatchParams},
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tracing::debug;
+
+use crate::core::{
+    resource::{constantes::*, error, service::ServicePort},
+    swappable::Swappable,
+};
+
+const SERVICE_NAME_LABEL: &str = "kubernetes.io/service-name";
+const MANAGED_BY_LABEL: &str = "endpointslice.kubernetes.io/managed-by";
+
+pub static REDIRECTION: Swappable<Redirection> = Swappable::new();
+
+/// How the traffic of an asleep Service is redirected to kubesleeper
+#[derive(Eq, PartialEq, Debug, Serialize, Deserialize, Clone, Copy, Default, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Redirection {
+    /// Swap the Service selector to select kubesleeper pods.
+    ///
+    /// Only works for Services in the namespace of kubesleeper.
+    #[default]
+    Selector,
+
+    /// Remove the Service selector and manage an EndpointSlice targeting kubesleeper pods.
+    ///
+    /// Works for Services of any namespace.
+    EndpointSlice,
+}
+
+pub fn redirection() -> Redirection {
+    REDIRECTION.get().unwrap_or_default()
+}
+
+fn endpoint_slice_name(service_name: &str) -> String {
+    format!("{service_name}-{KUBESLLEPER_APP_NAME}")
+}
+
+/// IPs of all ready kubesleeper pods
+async fn get_kubesleeper_pod_ips(client: Client) -> Result<Vec<String>, error::Resource> {
+    let lp = ListParams::default().labels(&format!(
+        "{KUBESLEEPER_SELECTOR_KEY}={KUBESLEEPER_SELECTOR_VALUE}"
+    ));
+
+    let mut ips: Vec<String> = Api::<Pod>::all(client)
+        .list(&lp)
+        .await?
+        .into_iter()
+        .filter_map(|pod| pod.status)
+        .filter(|status| {
+            status
+                .conditions
+                .iter()
+                .flatten()
+                .any(|c| c.type_ == "Ready" && c.status == "True")
+        })
+        .filter_map(|status| status.pod_ip)
+        .collect();
+    ips.sort();
+    Ok(ips)
+}
+
+fn build_endpoint_slice(
+    namespace: &str,
+    service_name: &str,
+    ports: Vec<EndpointPort>,
+    ips: Vec<String>,
+) -> EndpointSlice {
+    let address_type = if ips.iter().any(|ip| ip.contains(':')) {
+        "IPv6"
+    } else {
+        "IPv4"
+    };
+
+    EndpointSlice {
+        address_type: address_type.to_string(),
+        endpoints: ips
+            .into_iter()
+            .map(|ip| Endpoint {
+                addresses: vec![ip],
+                conditions: Some(EndpointConditions {
+                    ready: Some(true),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+            .collect(),
+        metadata: ObjectMeta {
+            name: Some(endpoint_slice_name(service_name)),
+            namespace: Some(namespace.to_string()),
+            labels: Some(BTreeMap::from([
+                (SERVICE_NAME_LABEL.to_string(), service_name.to_string()),
+                (
+                    MANAGED_BY_LABEL.to_string(),
+                    KUBESLLEPER_APP_NAME.to_string(),
+                ),
+            ])),
+            ..Default::default()
+        },
+        ports: Some(ports),
+    }
+}
+
+async fn apply_endpoint_slice(
+    client: Client,
+    slice: &EndpointSlice,
+) -> Result<(), error::Resource> {
+    let name = slice.name_any();
+    let namespace = slice.namespace().unwrap_or_default();
+    if super::dry_run::skip_patch(
+        "EndpointSlice",
+        &format!("{namespace}/{name}"),
+        &serde_json::to_value(slice)?,
+    ) {
+        return Ok(());
+    }
+    debug!("Applying EndpointSlice '{namespace}/{name}'");
+
+    Api::<EndpointSlice>::namespaced(client, &namespace)
+        .patch(
+            &name,
+            &PatchParams::apply(KUBESLLEPER_APP_NAME).force(),
+            &Patch::Apply(slice),
+        )
+        .await?;
+    Ok(())
+}
+
+/// Create (or update) the EndpointSlice redirecting the traffic of a Service to kubesleeper pods
+pub async fn create_endpoint_slice(
+    namespace: &str,
+    service_name: &str,
+    ports: &[ServicePort],
+) -> Result<(), error::Resource> {
+    let client = Client::try_default().await?;
+    let ips = get_kubesleeper_pod_ips(client.clone()).await?;
+
+    // Service ports are matched by name, the targetPort of the Service is ignored
+    let ports = ports
+        .iter()
+        .map(|p| EndpointPort {
+            name: p.name.clone(),
+            port: Some(KUBESLEEPER_SERVER_PORT),
+            protocol: p.protocol.clone(),
+            ..Default::default()
+        })
+        .collect();
+
+    apply_endpoint_slice(
+        client,
+        &build_endpoint_slice(namespace, service_name, ports, ips),
+    )
+    .await
+}
+
+/// Delete the EndpointSlice of a Service, if any
+pub async fn delete_endpoint_slice(
+    namespace: &str,
+    service_name: &str,
+) -> Result<(), error::Resource> {
+    let name = endpoint_slice_name(service_name);
+    if super::dry_run::is_enabled() {
+        debug!("Dry run : EndpointSlice '{namespace}/{name}' not deleted");
+        return Ok(());
+    }
+    let client = Client::try_default().await?;
+
+    match Api::<EndpointSlice>::namespaced(client, namespace)
+        .delete(&name, &DeleteParams::default())
+        .await
+    {
+        Ok(_) => {
+            debug!("EndpointSlice '{namespace}/{name}' deleted");
+            Ok(())
+        }
+        Err(kube::Error::Api(e)) if e.code == 404 => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Update all kubesleeper EndpointSlices with the current kubesleeper pod IPs
+/// (kubesleeper pods may have been restarted since Services were set asleep)
+pub async fn sync_endpoint_slices() -> Result<(), error::Resource> {
+    let client = Client::try_default().await?;
+    let ips = get_kubesleeper_pod_ips(client.clone()).await?;
+
+    let lp = ListParams::default().labels(&format!("{MANAGED_BY_LABEL}={KUBESLLEPER_APP_NAME}"));
+    for slice in Api::<EndpointSlice>::all(client.clone()).list(&lp).await? {
+        let mut slice_ips: Vec<String> = slice
+            .endpoints
+            .iter()
+            .flat_map(|e| e.addresses.clone())
+            .collect();
+        slice_ips.sort();
+        if slice_ips == ips {
+            continue;
+        }
+
+        let namespace = slice.namespace().unwrap_or_default();
+        let service_name = slice
+            .labels()
+            .get(SERVICE_NAME_LABEL)
+            .cloned()
+            .unwrap_or_default();
+        apply_endpoint_slice(
+            client.clone(),
+            &build_endpoint_slice(
+                &namespace,
+                &service_name,
+                slice.ports.unwrap_or_default(),
+                ips.clone(),
+            ),
+        )
+        .await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,76 @@
+//! Workloads set asleep by scaling them to 0 replicas : Deployments, StatefulSets and
+//! scalable kinds. Their replicas are stored in an annotation to be restored on wake.
+
+use std::time::Duration;
+use tracing::info;
+
+use crate::core::resource::{TargetResource, annotations::Annotations, constantes::*, error};
+
+/// Replicas to restore on wake : the current `replicas`, or the stored replicas annotation
+/// of an asleep workload
+pub fn store_replicas(
+    id: &str,
+    replicas: i32,
+    annotations: &Annotations,
+) -> Result<i32, error::ResourceParse> {
+    if replicas != 0 {
+        return Ok(replicas);
+    }
+
+    let raw_store_replicas = annotations.get(ANNOTATION_STORE_REPLICAS_KEY).ok_or(
+        error::ResourceParse::MissingAnnotationInSleepState {
+            id: id.to_string(),
+            annotation: format!("{KUBESLEEPER_ANNOTATION_PREFIX}{ANNOTATION_STORE_REPLICAS_KEY}"),
+        },
+    )?;
+    raw_store_replicas
+        .parse::<i32>()
+        .map_err(|err| error::ResourceParse::ParseFailed {
+            id: id.to_string(),
+            value: format!(
+                ".annotations.{KUBESLEEPER_ANNOTATION_PREFIX}{ANNOTATION_STORE_REPLICAS_KEY}"
+            ),
+            error: format!("{err}"),
+        })
+}
+
+/// A workload scaled to 0 replicas while asleep
+pub trait Replicated: TargetResource<'static> {
+    /// Kind of the workload (like 'Deployment')
+    fn kind(&self) -> &str;
+
+    fn replicas(&self) -> i32;
+
+    async fn get_ready_replicas_count(&self) -> Result<i32, error::Resource>;
+
+    /// Wait until all replicas are ready, polling with an exponential backoff
+    async fn wait_ready(&self) -> Result<(), error::Resource> {
+        // the replicas haven't been patched in dry run mode
+        if super::dry_run::is_enabled() {
+            return Ok(());
+        }
+
+        let (kind, id, replicas) = (self.kind(), self.id(), self.replicas());
+        let mut total_duration = 0;
+        for i in 0_u32..1000 {
+            let current_ready_replicas = self.get_ready_replicas_count().await?;
+            if replicas == current_ready_replicas {
+                info!("{kind} {id} just woke up.");
+                return Ok(());
+            }
+
+            info!(
+                "{kind} {id} is waking up. Waiting for replicas to be ready : {current_ready_replicas}/{replicas}"
+            );
+
+            let duration = 100 * 2_u64.pow([i, 7].into_iter().min().expect("Couldn't be empty"));
+            tokio::time::sleep(Duration::from_millis(duration)).await;
+            total_duration += duration;
+        }
+
+        Err(error::Resource::MaxWaitingWakeTime {
+            id,
+            max_waiting_time: total_duration,
+        })
+    }
+}
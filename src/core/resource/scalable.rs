@@ -0,0 +1,400 @@
+use k8s_openapi::api::autoscaling::v1::Scale;
+use kube::{
+    Api, Client, ResourceExt,
+    api::{ApiResource, DynamicObject, GroupVersionKind, ListParams, Patch, PatchParams, TypeMeta},
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use std::{collections::BTreeMap, fmt};
+use tracing::{debug, warn};
+
+use crate::core::{
+    resource::{
+        TargetResource,
+        annotations::Annotations,
+        constantes::*,
+        error,
+        hpa::ScaleTarget,
+        management::is_managed,
+        namespaces::is_watched,
+        replicas::{Replicated, store_replicas},
+    },
+    state::state_kind::StateKind,
+    swappable::Swappable,
+};
+
+pub static SCALABLE_KINDS: Swappable<Vec<ScalableKind>> = Swappable::new();
+
+/// A kind of resource exposing the `/scale` subresource (like Argo Rollouts)
+#[derive(Serialize, Debug, Deserialize, Clone, Eq, PartialEq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ScalableKind {
+    /// API group of the kind (like 'argoproj.io')
+    pub group: String,
+
+    /// API version of the kind (like 'v1alpha1')
+    pub version: String,
+
+    /// Name of the kind (like 'Rollout')
+    pub kind: String,
+
+    /// Plural name of the kind, guessed from `kind` if not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plural: Option<String>,
+}
+
+impl ScalableKind {
+    fn api_resource(&self) -> ApiResource {
+        let gvk = GroupVersionKind::gvk(&self.group, &self.version, &self.kind);
+        match &self.plural {
+            Some(plural) => ApiResource::from_gvk_with_plural(&gvk, plural),
+            None => ApiResource::from_gvk(&gvk),
+        }
+    }
+}
+
+fn scalable_kinds() -> Vec<ScalableKind> {
+    SCALABLE_KINDS.get().unwrap_or_default()
+}
+
+/// API resource of the configured scalable kind of `object`
+fn configured_api_resource(
+    object: &DynamicObject,
+    id: &str,
+) -> Result<ApiResource, error::ResourceParse> {
+    let types = object
+        .types
+        .as_ref()
+        .ok_or(error::ResourceParse::MissingValue {
+            id: id.to_string(),
+            value: ".apiVersion".to_string(),
+        })?;
+
+    scalable_kinds()
+        .iter()
+        .map(ScalableKind::api_resource)
+        .find(|ar| ar.api_version == types.api_version && ar.kind == types.kind)
+        .ok_or(error::ResourceParse::ParseFailed {
+            id: id.to_string(),
+            value: ".kind".to_string(),
+            error: format!(
+                "'{}/{}' is not a configured scalable kind",
+                types.api_version, types.kind
+            ),
+        })
+}
+
+/// Any resource scaled through its `/scale` subresource
+#[derive(Serialize)]
+pub struct Scalable {
+    pub id: String,
+    pub name: String,
+    pub namespace: String,
+    pub kind: String,
+    pub replicas: i32,
+    pub group: Option<String>,
+    pub sleepiness_duration: Option<Duration>,
+
+    pub store_replicas: i32,
+
+    #[serde(skip)]
+    api_resource: ApiResource,
+}
+
+impl TryFrom<(&DynamicObject, &Scale)> for Scalable {
+    type Error = error::Resource;
+
+    /// Parse an object of a scalable kind, with its replicas read from its scale subresource
+    fn try_from(
+        (object, scale): (&DynamicObject, &Scale),
+    ) -> std::result::Result<Self, Self::Error> {
+        // --- explicit data
+
+        let name = object.name_any();
+
+        let namespace = object
+            .namespace()
+            .ok_or(error::ResourceParse::MissingValue {
+                id: name.clone(),
+                value: "namespace".to_string(),
+            })?;
+
+        let id = format!("{namespace}/{name}");
+
+        let api_resource = configured_api_resource(object, &id)?;
+
+        let replicas = scale.spec.as_ref().and_then(|s| s.replicas).ok_or(
+            error::ResourceParse::MissingValue {
+                id: id.clone(),
+                value: "scale .spec.replicas".to_string(),
+            },
+        )?;
+
+        // --- store annotation
+        let raw_annotations = object.metadata.annotations.as_ref();
+        let annotations = Annotations::from(raw_annotations.unwrap_or(&BTreeMap::default()));
+
+        let store_replicas = store_replicas(&id, replicas, &annotations)?;
+
+        let group = annotations.get(ANNOTATION_GROUP_KEY).map(str::to_string);
+        let sleepiness_duration = annotations.sleepiness_duration(&id);
+
+        Ok(Scalable {
+            id,
+            name,
+            namespace,
+            kind: api_resource.kind.clone(),
+            replicas,
+            group,
+            sleepiness_duration,
+            store_replicas,
+            api_resource,
+        })
+    }
+}
+
+impl Scalable {
+    pub async fn get_k8s_api(
+        api_resource: &ApiResource,
+        namespace: Option<&str>,
+    ) -> Result<Api<DynamicObject>, error::Resource> {
+        let client = Client::try_default().await?;
+
+        let objects: Api<DynamicObject> = if let Some(namespace_name) = namespace {
+            Api::namespaced_with(client, namespace_name, api_resource)
+        } else {
+            Api::all_with(client, api_resource)
+        };
+
+        Ok(objects)
+    }
+
+    /// Parse `object`, reading its replicas from its scale subresource
+    async fn from_object(object: &DynamicObject) -> Result<Scalable, error::Resource> {
+        let namespace = object.namespace();
+        let api_resource = configured_api_resource(object, &object.name_any())?;
+        let scale = Self::get_k8s_api(&api_resource, namespace.as_deref())
+            .await?
+            .get_scale(&object.name_any())
+            .await?;
+        Scalable::try_from((object, &scale))
+    }
+
+    pub fn scale_target(&self) -> ScaleTarget<'_> {
+        (&self.namespace, &self.kind, &self.name)
+    }
+}
+
+impl Replicated for Scalable {
+    fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    fn replicas(&self) -> i32 {
+        self.replicas
+    }
+
+    /// Get ready replicas from `.status.readyReplicas`, or from the scale subresource
+    /// status if the kind doesn't have this field
+    async fn get_ready_replicas_count(&self) -> Result<i32, error::Resource> {
+        let ready_replicas = self.get_k8s_resource().await?.data["status"]["readyReplicas"]
+            .as_i64()
+            .and_then(|r| i32::try_from(r).ok());
+
+        match ready_replicas {
+            Some(ready_replicas) => Ok(ready_replicas),
+            None => Ok(Self::get_k8s_api(&self.api_resource, Some(&self.namespace))
+                .await?
+                .get_scale(&self.name)
+                .await?
+                .status
+                .map(|s| s.replicas)
+                .unwrap_or_default()),
+        }
+    }
+}
+
+impl TargetResource<'static> for Scalable {
+    type K8sResource = DynamicObject;
+
+    fn is_asleep(&self) -> bool {
+        self.replicas == 0
+    }
+
+    async fn wake(&mut self) -> Result<(), error::Resource> {
+        // skip if resource as already a 'awake' stored state
+        if !self.is_asleep() {
+            debug!(
+                "State of {} '{}' already marked as '{}', skipping wake action",
+                self.kind,
+                self.id,
+                StateKind::Awake
+            );
+            return Ok(());
+        }
+
+        // edit resource to set it in a awake state
+        self.replicas = self.store_replicas;
+
+        // patch related k8s resource
+        self.patch().await?;
+
+        self.wait_ready().await
+    }
+
+    async fn sleep(&mut self) -> Result<(), error::Resource> {
+        // skip if resource as already a 'asleep' stored state
+        if self.is_asleep() {
+            debug!(
+                "State of {} '{}' already marked as '{}', skipping sleep action",
+                self.kind,
+                self.id,
+                StateKind::Asleep
+            );
+            return Ok(());
+        }
+
+        // edit resource to set it in a asleep state
+        self.store_replicas = self.replicas;
+        self.replicas = 0;
+
+        // patch related k8s resource
+        self.patch().await
+    }
+
+    async fn list_k8s_resources() -> Result<Vec<Self::K8sResource>, error::Resource> {
+        let lp = super::label_selector::list_params();
+
+        let mut objects = Vec::new();
+        for scalable_kind in scalable_kinds() {
+            let api_resource = scalable_kind.api_resource();
+
+            // items of a list don't have their type set
+            let types = TypeMeta {
+                api_version: api_resource.api_version.clone(),
+                kind: api_resource.kind.clone(),
+            };
+
+            // a kind which can't be listed (like a CRD which isn't installed) doesn't prevent
+            // the others from being managed
+            let list = match Self::get_k8s_api(&api_resource, None)
+                .await?
+                .list(&lp)
+                .await
+            {
+                Ok(list) => list,
+                Err(e) => {
+                    warn!("Skipping scalable kind '{}' : {e}", api_resource.kind);
+                    continue;
+                }
+            };
+
+            for mut object in list.items.into_iter().filter(is_watched) {
+                object.types = Some(types.clone());
+                objects.push(object);
+            }
+        }
+        Ok(objects)
+    }
+
+    /// Objects which can't be parsed are skipped, so they don't prevent the others from
+    /// being managed
+    async fn get_all() -> Result<Vec<Self>, error::Resource> {
+        let mut scalables = Vec::new();
+        for object in Self::list_k8s_resources()
+            .await?
+            .iter()
+            .filter(|s| is_managed(*s))
+        {
+            match Self::from_object(object).await {
+                Ok(scalable) => scalables.push(scalable),
+                Err(e) => warn!(
+                    "Skipping {} '{}/{}' : {e}",
+                    object.types.as_ref().map_or("?", |t| &t.kind),
+                    object.namespace().unwrap_or_default(),
+                    object.name_any()
+                ),
+            }
+        }
+        Ok(scalables)
+    }
+
+    async fn patch(&self) -> Result<(), error::Resource> {
+        // replicas are stored before scaling, so they are never lost
+        let store_patch = serde_json::json!({
+            "metadata": {
+                "annotations": {
+                    format!("{KUBESLEEPER_ANNOTATION_PREFIX}{ANNOTATION_STORE_REPLICAS_KEY}"): self.store_replicas.to_string()
+                }
+            }
+        });
+        let patch = serde_json::json!({
+            "spec" : {
+                "replicas": self.replicas
+            }
+        });
+        // both patches are logged in dry run mode
+        if super::dry_run::is_enabled() {
+            super::dry_run::skip_patch(&self.kind, &self.id, &store_patch);
+            super::dry_run::skip_patch(&format!("{}/scale", self.kind), &self.id, &patch);
+            return Ok(());
+        }
+
+        let params = PatchParams::default();
+        let api = Self::get_k8s_api(&self.api_resource, Some(&self.namespace)).await?;
+        api.patch(&self.name, &params, &Patch::Merge(&store_patch))
+            .await?;
+        api.patch_scale(&self.name, &params, &Patch::Merge(&patch))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_k8s_resource(&self) -> Result<Self::K8sResource, error::Resource> {
+        let lp = ListParams::default()
+            .match_any()
+            .fields(&format!("metadata.name={}", self.name));
+
+        Self::get_k8s_api(&self.api_resource, Some(&self.namespace))
+            .await?
+            .list(&lp)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or(error::Resource::K8sResourceNotFound {
+                id: self.id.clone(),
+            })
+    }
+
+    fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    fn group_annotation(&self) -> Option<&str> {
+        self.group.as_deref()
+    }
+
+    fn sleepiness_duration(&self) -> Option<Duration> {
+        self.sleepiness_duration
+    }
+}
+
+impl fmt::Display for Scalable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{}",
+            serde_yaml::to_string(&self)
+                .unwrap_or_else(|e| format!(
+                    "{e} : The structure should always be serializable at this point"
+                ))
+                .trim()
+        )?;
+        Ok(())
+    }
+}
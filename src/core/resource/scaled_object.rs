@@ -0,0 +1,283 @@
+use kube::{
+    Api, Client, ResourceExt,
+    api::{ApiResource, DynamicObject, GroupVersionKind, ListParams, Patch, PatchParams},
+};
+use serde::Serialize;
+use std::{collections::BTreeMap, fmt};
+use tracing::debug;
+
+use crate::core::{
+    resource::{
+        TargetResource, annotations::Annotations, constantes::*, error, hpa::ScaleTarget,
+        namespaces::is_watched,
+    },
+    state::state_kind::StateKind,
+};
+
+/// KEDA annotation pausing the autoscaling of a ScaledObject at a fixed replicas count
+const KEDA_PAUSED_REPLICAS_ANNOTATION: &str = "autoscaling.keda.sh/paused-replicas";
+
+/// Kind of the resource scaled by a ScaledObject when not set
+const KEDA_DEFAULT_TARGET_KIND: &str = "Deployment";
+
+/// A KEDA ScaledObject, paused at 0 replicas while its target is asleep
+#[derive(Serialize)]
+pub struct ScaledObject {
+    pub id: String,
+    pub name: String,
+    pub namespace: String,
+
+    /// Kind of the scaled resource (like 'Deployment')
+    pub target_kind: String,
+    /// Name of the scaled resource, in the namespace of the ScaledObject
+    pub target_name: String,
+
+    /// `autoscaling.keda.sh/paused-replicas` annotation
+    pub paused_replicas: Option<String>,
+    pub group: Option<String>,
+
+    pub store_paused_replicas: Option<String>,
+}
+
+impl TryFrom<&DynamicObject> for ScaledObject {
+    type Error = error::Resource;
+
+    fn try_from(object: &DynamicObject) -> std::result::Result<Self, Self::Error> {
+        // --- explicit data
+
+        let name = object.name_any();
+
+        let namespace = object
+            .namespace()
+            .ok_or(error::ResourceParse::MissingValue {
+                id: name.clone(),
+                value: "namespace".to_string(),
+            })?;
+
+        let id = format!("{namespace}/{name}");
+
+        let target = &object.data["spec"]["scaleTargetRef"];
+        let target_name = target["name"]
+            .as_str()
+            .ok_or(error::ResourceParse::MissingValue {
+                id: id.clone(),
+                value: ".spec.scaleTargetRef.name".to_string(),
+            })?
+            .to_string();
+        let target_kind = target["kind"]
+            .as_str()
+            .unwrap_or(KEDA_DEFAULT_TARGET_KIND)
+            .to_string();
+
+        let raw_annotations = object.metadata.annotations.as_ref();
+        let paused_replicas = raw_annotations
+            .and_then(|a| a.get(KEDA_PAUSED_REPLICAS_ANNOTATION))
+            .cloned();
+
+        // --- store annotation
+        let annotations = Annotations::from(raw_annotations.unwrap_or(&BTreeMap::default()));
+
+        // The store annotation is removed on wake : a ScaledObject paused at 0 without it
+        // has been paused by someone else and must stay paused.
+        // An empty stored value means the ScaledObject wasn't paused.
+        let store_paused_replicas = match annotations.get(ANNOTATION_STORE_PAUSED_REPLICAS_KEY) {
+            Some(stored) if paused_replicas.as_deref() == Some("0") => {
+                Some(stored.to_string()).filter(|s| !s.is_empty())
+            }
+            _ => paused_replicas.clone(),
+        };
+
+        let group = annotations.get(ANNOTATION_GROUP_KEY).map(str::to_string);
+
+        Ok(ScaledObject {
+            id,
+            name,
+            namespace,
+            target_kind,
+            target_name,
+            paused_replicas,
+            group,
+            store_paused_replicas,
+        })
+    }
+}
+
+impl ScaledObject {
+    fn api_resource() -> ApiResource {
+        let gvk = GroupVersionKind::gvk("keda.sh", "v1alpha1", "ScaledObject");
+        ApiResource::from_gvk(&gvk)
+    }
+
+    pub async fn get_k8s_api(
+        namespace: Option<&str>,
+    ) -> Result<Api<DynamicObject>, error::Resource> {
+        let client = Client::try_default().await?;
+
+        let objects: Api<DynamicObject> = if let Some(namespace_name) = namespace {
+            Api::namespaced_with(client, namespace_name, &Self::api_resource())
+        } else {
+            Api::all_with(client, &Self::api_resource())
+        };
+
+        Ok(objects)
+    }
+
+    /// Check if the ScaledObject scales `target`
+    pub fn scales(&self, (namespace, kind, name): ScaleTarget<'_>) -> bool {
+        self.namespace == namespace && self.target_kind == kind && self.target_name == name
+    }
+
+    /// Get all ScaledObjects scaling one of `targets`, ScaledObjects of unmanaged resources are
+    /// left untouched
+    pub async fn get_all_scaling(
+        targets: &[ScaleTarget<'_>],
+    ) -> Result<Vec<Self>, error::Resource> {
+        let mut scaled_objects = Self::get_all().await?;
+        scaled_objects.retain(|so| targets.iter().any(|target| so.scales(*target)));
+        Ok(scaled_objects)
+    }
+}
+
+impl TargetResource<'static> for ScaledObject {
+    type K8sResource = DynamicObject;
+
+    fn is_asleep(&self) -> bool {
+        self.paused_replicas.as_deref() == Some("0")
+    }
+
+    async fn wake(&mut self) -> Result<(), error::Resource> {
+        // skip if resource as already a 'awake' stored state
+        if !self.is_asleep() {
+            debug!(
+                "State of scaledobject '{}' already marked as '{}', skipping wake action",
+                self.id,
+                StateKind::Awake
+            );
+            return Ok(());
+        }
+
+        // edit resource to set it in a awake state
+        self.paused_replicas = self.store_paused_replicas.clone();
+
+        // patch related k8s resource
+        self.patch().await
+    }
+
+    async fn sleep(&mut self) -> Result<(), error::Resource> {
+        // skip if resource as already a 'asleep' stored state
+        if self.is_asleep() {
+            debug!(
+                "State of scaledobject '{}' already marked as '{}', skipping sleep action",
+                self.id,
+                StateKind::Asleep
+            );
+            return Ok(());
+        }
+
+        // edit resource to set it in a asleep state
+        self.store_paused_replicas = self.paused_replicas.clone();
+        self.paused_replicas = Some("0".to_string());
+
+        // patch related k8s resource
+        self.patch().await
+    }
+
+    async fn list_k8s_resources() -> Result<Vec<Self::K8sResource>, error::Resource> {
+        // not restricted by the label selector : autoscalers follow their workload
+        let lp = ListParams::default().match_any();
+
+        match Self::get_k8s_api(None).await?.list(&lp).await {
+            Ok(objects) => Ok(objects.items.into_iter().filter(is_watched).collect()),
+            // KEDA isn't installed in the cluster
+            Err(kube::Error::Api(e)) if e.code == 404 => {
+                debug!("ScaledObject kind not found, skipping KEDA resources");
+                Ok(Vec::new())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn get_all() -> Result<Vec<Self>, error::Resource> {
+        Self::list_k8s_resources()
+            .await?
+            .iter()
+            // not filtered by their annotation : autoscalers follow the management of their
+            // workload (see `get_all_scaling`)
+            .map(Self::try_from)
+            .collect()
+    }
+
+    async fn patch(&self) -> Result<(), error::Resource> {
+        // the store annotation is only kept while the ScaledObject is asleep
+        let store_paused_replicas = match self.is_asleep() {
+            true => {
+                serde_json::Value::String(self.store_paused_replicas.clone().unwrap_or_default())
+            }
+            false => serde_json::Value::Null,
+        };
+
+        let patch = serde_json::json!({
+            "metadata": {
+                "annotations": {
+                    KEDA_PAUSED_REPLICAS_ANNOTATION: self.paused_replicas,
+                    format!("{KUBESLEEPER_ANNOTATION_PREFIX}{ANNOTATION_STORE_PAUSED_REPLICAS_KEY}"): store_paused_replicas
+                }
+            }
+        });
+        if super::dry_run::skip_patch("ScaledObject", &self.id, &patch) {
+            return Ok(());
+        }
+
+        let params = PatchParams::default();
+        let patch = Patch::Merge(&patch);
+        Self::get_k8s_api(Some(&self.namespace))
+            .await?
+            .patch(&self.name, &params, &patch)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_k8s_resource(&self) -> Result<Self::K8sResource, error::Resource> {
+        let lp = ListParams::default()
+            .match_any()
+            .fields(&format!("metadata.name={}", self.name));
+
+        Self::get_k8s_api(Some(&self.namespace))
+            .await?
+            .list(&lp)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or(error::Resource::K8sResourceNotFound {
+                id: self.id.clone(),
+            })
+    }
+
+    fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    fn group_annotation(&self) -> Option<&str> {
+        self.group.as_deref()
+    }
+}
+
+impl fmt::Display for ScaledObject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{}",
+            serde_yaml::to_string(&self)
+                .unwrap_or_else(|e| format!(
+                    "{e} : The structure should always be serializable at this point"
+                ))
+                .trim()
+        )?;
+        Ok(())
+    }
+}
@@ -1,5 +1,11 @@
 use crate::core::resource::TargetResource;
-use crate::core::resource::{annotations::Annotations, constantes::*};
+use crate::core::resource::{
+    annotations::Annotations,
+    constantes::*,
+    management::is_managed,
+    namespaces::is_watched,
+    redirection::{self, Redirection},
+};
 
 use crate::core::state::state_kind::StateKind;
 
@@ -18,14 +24,19 @@ use std::{
     collections::{BTreeMap, HashMap},
     fmt,
     string::ToString,
+    time::Duration,
 };
 use tracing::debug;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ServicePort {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
     pub port: i32,
     #[serde(rename = "targetPort")]
     pub target_port: IntOrString,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protocol: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -34,6 +45,8 @@ pub struct Service {
     pub name: String,
     pub namespace: String,
     pub selector: HashMap<String, String>,
+    pub group: Option<String>,
+    pub sleepiness_duration: Option<Duration>,
 
     // using i32 as key and not name cause name is optional.
     // IntOrString cause it could be map 80:myPort service side,
@@ -46,16 +59,26 @@ pub struct Service {
     pub store_ports: Vec<ServicePort>,
 }
 
+impl Service {
+    pub async fn get_k8s_api(namespace: Option<&str>) -> Result<Api<K8sService>, error::Resource> {
+        let client = Client::try_default().await?;
+
+        let deployments: Api<K8sService> = if let Some(namespace_name) = namespace {
+            Api::namespaced(client, namespace_name)
+        } else {
+            Api::all(client)
+        };
+
+        return Ok(deployments);
+    }
+}
+
 impl TargetResource<'static> for Service {
     type K8sResource = K8sService;
 
     fn is_asleep(&self) -> bool {
-        self.selector.len() == 1
-            && self
-                .selector
-                .get(KUBESLEEPER_SELECTOR_KEY)
-                .map(|v| v == KUBESLEEPER_SELECTOR_VALUE)
-                .unwrap_or(false)
+        is_kubesleeper_selector(&self.selector)
+            || (self.selector.is_empty() && !self.store_selector.is_empty())
     }
 
     async fn wake(&mut self) -> Result<(), error::Resource> {
@@ -69,13 +92,22 @@ impl TargetResource<'static> for Service {
             return Ok(());
         }
 
+        // only Services set asleep with the 'endpoint_slice' redirection have no selector
+        // (and an EndpointSlice), even if the redirection has been changed since
+        let redirected_by_endpoint_slice = self.selector.is_empty();
+
         // edit resource to set it in a awake state
 
         self.selector = self.store_selector.clone();
 
         self.ports = self.store_ports.clone();
 
-        self.patch().await
+        self.patch().await?;
+
+        if redirected_by_endpoint_slice {
+            redirection::delete_endpoint_slice(&self.namespace, &self.name).await?;
+        }
+        Ok(())
     }
 
     async fn sleep(&mut self) -> Result<(), error::Resource> {
@@ -89,21 +121,40 @@ impl TargetResource<'static> for Service {
             return Ok(());
         }
 
+        // Services without selector have user managed endpoints, they can't be redirected
+        if self.selector.is_empty() {
+            debug!(
+                "Service '{}' has no selector, skipping sleep action",
+                self.id
+            );
+            return Ok(());
+        }
+
         // edit resource to set it in a asleep state
         self.store_selector = self.selector.clone();
         self.store_ports = self.ports.clone();
-
         self.selector = HashMap::new();
-        self.selector.insert(
-            KUBESLEEPER_SELECTOR_KEY.to_string(),
-            KUBESLEEPER_SELECTOR_VALUE.to_string(),
-        );
 
-        self.ports
-            .iter_mut()
-            .for_each(|sp| sp.target_port = IntOrString::Int(KUBESLEEPER_SERVER_PORT));
+        match redirection::redirection() {
+            Redirection::Selector => {
+                self.selector.insert(
+                    KUBESLEEPER_SELECTOR_KEY.to_string(),
+                    KUBESLEEPER_SELECTOR_VALUE.to_string(),
+                );
+
+                self.ports
+                    .iter_mut()
+                    .for_each(|sp| sp.target_port = IntOrString::Int(KUBESLEEPER_SERVER_PORT));
 
-        self.patch().await
+                self.patch().await
+            }
+            Redirection::EndpointSlice => {
+                // the EndpointSlice is created first so the Service never has no endpoint
+                redirection::create_endpoint_slice(&self.namespace, &self.name, &self.ports)
+                    .await?;
+                self.patch().await
+            }
+        }
     }
 
     async fn patch(&self) -> Result<(), error::Resource> {
@@ -111,9 +162,15 @@ impl TargetResource<'static> for Service {
 
         let store_ports = serde_json::to_string(&self.store_ports)?;
 
+        // an empty map would be ignored by the merge patch, null removes the selector
+        let selector = match self.selector.is_empty() {
+            true => serde_json::Value::Null,
+            false => serde_json::to_value(&self.selector)?,
+        };
+
         let patch = serde_json::json!({
             "spec" : {
-                "selector": self.selector,
+                "selector": selector,
                 "ports": self.ports
             },
             "metadata": {
@@ -124,6 +181,10 @@ impl TargetResource<'static> for Service {
             }
         });
 
+        if super::dry_run::skip_patch("Service", &self.id, &patch) {
+            return Ok(());
+        }
+
         let params = PatchParams::default();
         let patch = Patch::Merge(&patch);
         Service::get_k8s_api(Some(&self.namespace))
@@ -134,31 +195,27 @@ impl TargetResource<'static> for Service {
         Ok(())
     }
 
-    async fn get_k8s_api(
-        namespace: Option<&str>,
-    ) -> Result<Api<Self::K8sResource>, error::Resource> {
-        let client = Client::try_default().await?;
-
-        let deployments: Api<Self::K8sResource> = if let Some(namespace_name) = namespace {
-            Api::namespaced(client, namespace_name)
-        } else {
-            Api::all(client)
-        };
-
-        return Ok(deployments);
-    }
-
-    async fn get_all() -> Result<Vec<Self>, error::Resource> {
-        let lp = ListParams::default().match_any().fields(&format!(
-            "metadata.name!=kubernetes,metadata.namespace!=kube-system,metadata.name!={}",
+    async fn list_k8s_resources() -> Result<Vec<Self::K8sResource>, error::Resource> {
+        let lp = super::label_selector::list_params().fields(&format!(
+            "metadata.name!=kubernetes,metadata.name!={}",
             KUBESLLEPER_APP_NAME
         ));
-        Self::get_k8s_api(None)
+        Ok(Self::get_k8s_api(None)
             .await?
             .list(&lp)
+            .await?
+            .items
+            .into_iter()
+            .filter(is_watched)
+            .collect())
+    }
+
+    async fn get_all() -> Result<Vec<Self>, error::Resource> {
+        Self::list_k8s_resources()
             .await?
             .iter()
-            .map(|d| Self::try_from(d))
+            .filter(|s| is_managed(*s))
+            .map(|s| Self::try_from(s))
             .collect()
     }
 
@@ -181,6 +238,27 @@ impl TargetResource<'static> for Service {
     fn id(&self) -> String {
         return self.id.clone();
     }
+
+    fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    fn group_annotation(&self) -> Option<&str> {
+        self.group.as_deref()
+    }
+
+    fn sleepiness_duration(&self) -> Option<Duration> {
+        self.sleepiness_duration
+    }
+}
+
+/// Check if a selector is the one used to redirect traffic to kubesleeper
+fn is_kubesleeper_selector(selector: &HashMap<String, String>) -> bool {
+    selector.len() == 1
+        && selector
+            .get(KUBESLEEPER_SELECTOR_KEY)
+            .map(|v| v == KUBESLEEPER_SELECTOR_VALUE)
+            .unwrap_or(false)
 }
 
 impl fmt::Display for Service {
@@ -218,15 +296,12 @@ impl TryFrom<&K8sService> for Service {
 
         let id = format!("{namespace}/{name}");
 
+        // Services without selector are valid (and asleep Services with 'endpoint_slice' redirection have none)
         let selector: HashMap<String, String> = service
             .spec
             .as_ref()
-            .and_then(|s| s.selector.as_ref())
-            .ok_or_else(|| error::ResourceParse::MissingValue {
-                id: format!("{id}"),
-                value: format!(".spec.selector"),
-            })?
-            .clone()
+            .and_then(|s| s.selector.clone())
+            .unwrap_or_default()
             .into_iter()
             .collect();
 
@@ -241,10 +316,12 @@ impl TryFrom<&K8sService> for Service {
             .clone()
             .into_iter()
             .map(|svc_port| ServicePort {
+                name: svc_port.name,
                 port: svc_port.port,
                 target_port: svc_port
                     .target_port
                     .unwrap_or(IntOrString::Int(svc_port.port)),
+                protocol: svc_port.protocol,
             })
             .collect();
         let raw_annotations = service.metadata.annotations.as_ref();
@@ -252,11 +329,8 @@ impl TryFrom<&K8sService> for Service {
 
         // --- store annotation
 
-        let is_asleep = selector.len() == 1
-            && selector
-                .get(KUBESLEEPER_SELECTOR_KEY)
-                .map(|v| v == KUBESLEEPER_SELECTOR_VALUE)
-                .unwrap_or(false);
+        let is_asleep = is_kubesleeper_selector(&selector)
+            || (selector.is_empty() && annotations.get(ANNOTATION_STORE_SELECTOR_KEY).is_some());
 
         let store_selector = if is_asleep {
             annotations
@@ -310,11 +384,16 @@ impl TryFrom<&K8sService> for Service {
             ports.to_owned()
         };
 
+        let group = annotations.get(ANNOTATION_GROUP_KEY).map(str::to_string);
+        let sleepiness_duration = annotations.sleepiness_duration(&id);
+
         Ok(Service {
             id,
             name,
             namespace,
             selector,
+            group,
+            sleepiness_duration,
             ports,
             store_selector,
             store_ports,
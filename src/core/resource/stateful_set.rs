@@ -0,0 +1,276 @@
+use k8s_openapi::api::apps::v1::StatefulSet as K8sStatefulSet;
+use kube::{
+    Api, Client, ResourceExt,
+    api::{ListParams, Patch, PatchParams},
+    runtime::reflector::Lookup,
+};
+use serde::Serialize;
+use tracing::{debug, warn};
+
+use crate::core::{
+    resource::{constantes::*, error},
+    state::state_kind::StateKind,
+};
+use std::time::Duration;
+use std::{collections::BTreeMap, fmt};
+
+#[derive(Serialize)]
+pub struct StatefulSet {
+    pub id: String,
+    pub name: String,
+    pub namespace: String,
+    pub replicas: i32,
+    pub group: Option<String>,
+    pub sleepiness_duration: Option<Duration>,
+
+    pub store_replicas: i32,
+}
+impl TryFrom<&K8sStatefulSet> for StatefulSet {
+    type Error = error::Resource;
+
+    fn try_from(statefulset: &K8sStatefulSet) -> std::result::Result<Self, Self::Error> {
+        // --- explicit data
+
+        let name = statefulset
+            .name()
+            .ok_or(error::ResourceParse::MissingValue {
+                id: "?/?".to_string(),
+                value: "name".to_string(),
+            })?
+            .to_string();
+
+        let namespace =
+            ResourceExt::namespace(statefulset).ok_or(error::ResourceParse::MissingValue {
+                id: name.clone(),
+                value: "namespace".to_string(),
+            })?;
+
+        let id = format!("{namespace}/{name}");
+
+        let replicas = statefulset.spec.as_ref().and_then(|s| s.replicas).ok_or(
+            error::ResourceParse::MissingValue {
+                id: id.clone(),
+                value: ".spec".to_string(),
+            },
+        )?;
+
+        // --- store annotation
+        let raw_annotations = statefulset.metadata.annotations.as_ref();
+        let annotations =
+            super::annotations::Annotations::from(raw_annotations.unwrap_or(&BTreeMap::default()));
+
+        let store_replicas = store_replicas(&id, replicas, &annotations)?;
+
+        let group = annotations.get(ANNOTATION_GROUP_KEY).map(str::to_string);
+        let sleepiness_duration = annotations.sleepiness_duration(&id);
+
+        Ok(StatefulSet {
+            id,
+            name,
+            namespace,
+            replicas,
+            group,
+            sleepiness_duration,
+            store_replicas,
+        })
+    }
+}
+
+impl StatefulSet {
+    pub async fn get_k8s_api(
+        namespace: Option<&str>,
+    ) -> Result<Api<K8sStatefulSet>, error::Resource> {
+        let client = Client::try_default().await?;
+
+        let statefulsets: Api<K8sStatefulSet> = if let Some(namespace_name) = namespace {
+            Api::namespaced(client, namespace_name)
+        } else {
+            Api::all(client)
+        };
+
+        Ok(statefulsets)
+    }
+}
+
+impl super::TargetResource<'static> for StatefulSet {
+    type K8sResource = K8sStatefulSet;
+
+    fn is_asleep(&self) -> bool {
+        self.replicas == 0
+    }
+
+    async fn wake(&mut self) -> Result<(), error::Resource> {
+        // skip if resource as already a 'awake' stored state
+        if !self.is_asleep() {
+            debug!(
+                "State of statefulset '{}' already marked as '{}', skipping sleep action",
+                self.id,
+                StateKind::Awake
+            );
+            return Ok(());
+        }
+
+        // edit resource to set it in a awake state
+        self.replicas = self.store_replicas;
+
+        // patch related k8s resource
+        self.patch().await?;
+
+        self.wait_ready().await
+    }
+
+    async fn sleep(&mut self) -> Result<(), error::Resource> {
+        // skip if resource as already a 'asleep' stored state
+
+        if self.is_asleep() {
+            debug!(
+                "State of statefulset '{}' already marked as '{}', skipping sleep action",
+                self.id,
+                StateKind::Asleep
+            );
+            return Ok(());
+        }
+
+        // edit resource to set it in a asleep state
+        self.store_replicas = self.replicas;
+        self.replicas = 0;
+
+        // patch related k8s resource
+        self.patch().await
+    }
+
+    async fn list_k8s_resources() -> Result<Vec<Self::K8sResource>, error::Resource> {
+        let lp = super::label_selector::list_params();
+
+        Ok(Self::get_k8s_api(None)
+            .await?
+            .list(&lp)
+            .await?
+            .items
+            .into_iter()
+            .filter(super::namespaces::is_watched)
+            .collect())
+    }
+
+    /// Objects which can't be parsed (like a StatefulSet scaled to 0 by hand) are skipped, so
+    /// they don't prevent the others from being managed
+    async fn get_all() -> Result<Vec<Self>, error::Resource> {
+        Ok(Self::list_k8s_resources()
+            .await?
+            .iter()
+            .filter(|s| super::management::is_managed(*s))
+            .filter_map(|s| {
+                Self::try_from(s)
+                    .inspect_err(|e| warn!("Skipping StatefulSet : {e}"))
+                    .ok()
+            })
+            .collect())
+    }
+
+    async fn patch(&self) -> Result<(), error::Resource> {
+        let patch = serde_json::json!({
+            "spec" : {
+                "replicas": self.replicas
+            },
+            "metadata": {
+                "annotations": {
+                    format!("{KUBESLEEPER_ANNOTATION_PREFIX}{ANNOTATION_STORE_REPLICAS_KEY}"): self.store_replicas.to_string()
+                }
+            }
+        });
+        if super::dry_run::skip_patch("StatefulSet", &self.id, &patch) {
+            return Ok(());
+        }
+
+        let params = PatchParams::default();
+        let patch = Patch::Merge(&patch);
+        Self::get_k8s_api(Some(&self.namespace))
+            .await?
+            .patch(&self.name, &params, &patch)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_k8s_resource(&self) -> Result<Self::K8sResource, error::Resource> {
+        let lp = ListParams::default()
+            .match_any()
+            .fields(&format!("metadata.name={}", self.name));
+
+        Self::get_k8s_api(Some(&self.namespace))
+            .await?
+            .list(&lp)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or(error::Resource::K8sResourceNotFound {
+                id: self.id.clone(),
+            })
+    }
+
+    fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    fn group_annotation(&self) -> Option<&str> {
+        self.group.as_deref()
+    }
+
+    fn sleepiness_duration(&self) -> Option<Duration> {
+        self.sleepiness_duration
+    }
+}
+
+use super::{
+    TargetResource,
+    hpa::ScaleTarget,
+    replicas::{Replicated, store_replicas},
+};
+
+impl StatefulSet {
+    pub fn scale_target(&self) -> ScaleTarget<'_> {
+        (&self.namespace, "StatefulSet", &self.name)
+    }
+}
+
+impl Replicated for StatefulSet {
+    fn kind(&self) -> &str {
+        "StatefulSet"
+    }
+
+    fn replicas(&self) -> i32 {
+        self.replicas
+    }
+
+    async fn get_ready_replicas_count(&self) -> Result<i32, error::Resource> {
+        Ok(self
+            .get_k8s_resource()
+            .await?
+            .status
+            .ok_or(error::ResourceParse::MissingValue {
+                id: self.id.clone(),
+                value: "status".to_string(),
+            })?
+            .ready_replicas
+            .unwrap_or_default())
+    }
+}
+
+impl fmt::Display for StatefulSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{}",
+            serde_yaml::to_string(&self)
+                .unwrap_or_else(|e| format!(
+                    "{e} : The structure should always be serializable at this point"
+                ))
+                .trim()
+        )?;
+        Ok(())
+    }
+}
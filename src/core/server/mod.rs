@@ -2,11 +2,11 @@ use rocket::{fs::FileServer, routes};
 use std::num::NonZeroU16;
 use tracing::info;
 
-use crate::core::server::routes::{apps, static_catcher, wait};
+use crate::core::server::routes::{activity, apps, static_catcher, wait};
 
 mod routes;
 
-const KUBESLEEPER_REST_PATH_PREFIX: &str = "/kubesleeper";
+pub const KUBESLEEPER_REST_PATH_PREFIX: &str = "/kubesleeper";
 
 pub mod error {
     #[derive(Debug, thiserror::Error)]
@@ -27,7 +27,10 @@ pub async fn start(port: NonZeroU16) -> Result<(), error::ServerError> {
             KUBESLEEPER_REST_PATH_PREFIX.to_string() + "/static",
             FileServer::from("static"),
         )
-        .mount(KUBESLEEPER_REST_PATH_PREFIX, routes![wait, static_catcher])
+        .mount(
+            KUBESLEEPER_REST_PATH_PREFIX,
+            routes![wait, activity, static_catcher],
+        )
         .launch()
         .await?;
     Ok(())
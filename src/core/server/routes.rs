@@ -1,24 +1,19 @@
 use rocket::{
-    Request,
+    Config, Request,
     fs::NamedFile,
     get,
-    http::{ContentType, Status},
+    http::{ContentType, Status, uri::Host},
+    post,
     response::{Redirect, Responder, Response},
 };
-use tracing::{info, instrument};
+use tracing::{info, instrument, warn};
 
 use std::{
     io::Cursor,
     path::{Path, PathBuf},
 };
 
-use crate::core::{
-    server::KUBESLEEPER_REST_PATH_PREFIX,
-    state::{
-        notification::{Notification, NotificationKind},
-        state::State,
-    },
-};
+use crate::core::{leader, server::KUBESLEEPER_REST_PATH_PREFIX, state::state::State};
 
 pub enum AppResponse {
     Success(Redirect),
@@ -78,18 +73,45 @@ pub async fn wait() -> Option<NamedFile> {
     NamedFile::open(Path::new("static/waiting.html")).await.ok()
 }
 
+/// activity forwarded by another replica, for the leader
+#[post("/activity?<host>")]
+#[instrument(name = "server", level = "info")]
+pub async fn activity(host: Option<String>) -> Status {
+    info!("POST {}/activity", KUBESLEEPER_REST_PATH_PREFIX);
+    if !leader::is_leader() {
+        warn!("Activity received while not being the leader");
+        return Status::ServiceUnavailable;
+    }
+
+    match State::update_from_host_activity(host.as_deref()).await {
+        Ok(_) => Status::NoContent,
+        Err(e) => {
+            warn!("Failed to update state from forwarded activity : {e}");
+            Status::InternalServerError
+        }
+    }
+}
+
 /// catch all route and redirect to /ks/wait
 #[get("/<path..>")]
-#[instrument(name = "server", level = "info")]
-pub async fn apps(path: PathBuf) -> AppResponse {
+#[instrument(name = "server", level = "info", skip(config))]
+pub async fn apps(path: PathBuf, host: Option<&Host<'_>>, config: &Config) -> AppResponse {
     if path.starts_with(KUBESLEEPER_REST_PATH_PREFIX) {
         return AppResponse::Ignored;
     };
 
     info!("GET /{}", path.to_string_lossy());
 
-    let update =
-        State::update_from_notification(Notification::new(NotificationKind::Activity)).await;
+    let host = host.map(|h| h.domain().to_string());
+    // only the leader updates the state
+    let update = match leader::is_leader() {
+        true => State::update_from_host_activity(host.as_deref())
+            .await
+            .map_err(|e| e.to_string()),
+        false => leader::forward_activity(host.as_deref(), config.port)
+            .await
+            .map_err(|e| e.to_string()),
+    };
     match update {
         Ok(_) => AppResponse::Success(Redirect::to(format!("{KUBESLEEPER_REST_PATH_PREFIX}/wait"))),
         Err(e) => AppResponse::InternalError(e.to_string()),
@@ -0,0 +1,610 @@
+//! Holiday calendars from iCalendar (.ics) files : all groups are set asleep when a holiday
+//! starts, and schedules don't wake them up until it ends. Traffic still wakes them.
+//!
+//! Only the events (VEVENT) are read, with their DTSTART, DTEND, SUMMARY and yearly RRULE.
+//! Times with a TZID are read in this time zone, other local times in the configured timezone.
+
+use chrono_tz::Tz;
+use core::fmt;
+use k8s_openapi::chrono::{DateTime, Datelike, Days, Months, NaiveDate, NaiveDateTime, Utc};
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+use crate::core::{state::schedule::Timezone, swappable::Swappable};
+
+pub static CALENDAR: Swappable<Calendar> = Swappable::new();
+
+/// Holiday in progress at the last check, so a holiday is applied once
+static ENTERED: Swappable<Option<Occurrence>> = Swappable::new();
+
+#[derive(Debug, thiserror::Error)]
+pub enum CalendarError {
+    #[error("Failed to read holidays calendar '{path}' : {err}")]
+    IOError { path: PathBuf, err: std::io::Error },
+
+    #[error("Invalid holidays calendar '{path}', line {line} : {message}")]
+    Invalid {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+}
+
+/// Holidays of an iCalendar file
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Calendar {
+    holidays: Vec<Holiday>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Holiday {
+    summary: String,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    /// Repeated every year (`RRULE:FREQ=YEARLY`)
+    yearly: bool,
+}
+
+/// A holiday, from its start to its end (excluded)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Occurrence {
+    pub summary: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+impl fmt::Display for Occurrence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "'{}' ({} to {})",
+            self.summary,
+            self.start.to_rfc3339(),
+            self.end.to_rfc3339()
+        )
+    }
+}
+
+/// Next change of the calendar : a holiday starting or ending
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Transition {
+    Start(Occurrence),
+    End(Occurrence),
+}
+
+impl Transition {
+    pub fn time(&self) -> DateTime<Utc> {
+        match self {
+            Transition::Start(o) => o.start,
+            Transition::End(o) => o.end,
+        }
+    }
+}
+
+impl fmt::Display for Transition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Transition::Start(o) => {
+                write!(f, "start of holiday '{}' (all groups asleep)", o.summary)
+            }
+            Transition::End(o) => write!(f, "end of holiday '{}'", o.summary),
+        }
+    }
+}
+
+impl Holiday {
+    /// Occurrences of the holiday around `now` : the previous, current and next years
+    /// of a yearly holiday
+    fn occurrences(&self, now: DateTime<Utc>) -> Vec<Occurrence> {
+        let years = match self.yearly {
+            true => {
+                let elapsed = now.year() - self.start.year();
+                (elapsed - 1).max(0)..=(elapsed + 1).max(0)
+            }
+            false => 0..=0,
+        };
+        years
+            .filter_map(|years| {
+                let start = self
+                    .start
+                    .checked_add_months(Months::new(12 * years as u32))?;
+                Some(Occurrence {
+                    summary: self.summary.clone(),
+                    start,
+                    end: start + (self.end - self.start),
+                })
+            })
+            .collect()
+    }
+}
+
+impl Calendar {
+    /// Read the calendar file at `path`, with local times in `timezone`
+    pub fn load(path: &Path, timezone: Timezone) -> Result<Calendar, CalendarError> {
+        let raw = std::fs::read_to_string(path).map_err(|err| CalendarError::IOError {
+            path: path.to_path_buf(),
+            err,
+        })?;
+        let (calendar, warnings) =
+            Calendar::parse(&raw, timezone).map_err(|(line, message)| CalendarError::Invalid {
+                path: path.to_path_buf(),
+                line,
+                message,
+            })?;
+        for (line, message) in warnings {
+            warn!(
+                "Holidays calendar '{}', line {line} : {message}",
+                path.display()
+            );
+        }
+        debug!(
+            "{} holiday(s) read from {}",
+            calendar.holidays.len(),
+            path.display()
+        );
+        Ok(calendar)
+    }
+
+    /// Parse an iCalendar content, with local times in `timezone`.
+    ///
+    /// Errors are returned with their line, as well as the warnings of a valid calendar.
+    fn parse(raw: &str, timezone: Timezone) -> Result<ParsedCalendar, (usize, String)> {
+        let mut holidays = Vec::new();
+        let mut warnings = Vec::new();
+        let mut event: Option<Event> = None;
+
+        for (line, content) in unfold(raw) {
+            let (name, value) = content
+                .split_once(':')
+                .ok_or((line, format!("invalid content line '{content}'")))?;
+            let mut params = name.split(';');
+            let name = params.next().unwrap_or_default().to_ascii_uppercase();
+            let params: Vec<&str> = params.collect();
+
+            match (name.as_str(), event.as_mut()) {
+                ("BEGIN", None) if value.eq_ignore_ascii_case("VEVENT") => {
+                    event = Some(Event {
+                        line,
+                        ..Default::default()
+                    });
+                }
+                ("END", Some(_)) if value.eq_ignore_ascii_case("VEVENT") => {
+                    let e = event.take().expect("Event should be started at this point");
+                    let start_line = e.line;
+                    if let Some(holiday) = e.build(timezone).map_err(|m| (start_line, m))? {
+                        holidays.push(holiday);
+                    }
+                }
+                ("SUMMARY", Some(e)) => e.summary = Some(unescape(value)),
+                ("DTSTART" | "DTEND", Some(e)) => {
+                    let zone = match time_zone(&params) {
+                        Ok(zone) => zone.unwrap_or(timezone.0),
+                        Err(tzid) => {
+                            warnings.push((
+                                line,
+                                format!(
+                                    "unknown time zone '{tzid}', using the configured timezone"
+                                ),
+                            ));
+                            timezone.0
+                        }
+                    };
+                    let time = Time::parse(&params, value, zone).map_err(|m| (line, m))?;
+                    match name.as_str() {
+                        "DTSTART" => e.start = Some(time),
+                        _ => e.end = Some(time),
+                    }
+                }
+                ("RRULE", Some(e)) => match value.eq_ignore_ascii_case("FREQ=YEARLY") {
+                    true => e.yearly = true,
+                    false => warnings.push((
+                        line,
+                        format!(
+                            "unsupported recurrence rule '{value}', only the first occurrence is used"
+                        ),
+                    )),
+                },
+                _ => {}
+            }
+        }
+
+        match event {
+            Some(e) => Err((e.line, "event without END:VEVENT".to_string())),
+            None => Ok((Calendar { holidays }, warnings)),
+        }
+    }
+
+    /// Holiday in progress at `now`, the one ending last if they overlap
+    pub fn current(&self, now: DateTime<Utc>) -> Option<Occurrence> {
+        self.holidays
+            .iter()
+            .flat_map(|h| h.occurrences(now))
+            .filter(|o| o.start <= now && now < o.end)
+            .max_by_key(|o| o.end)
+    }
+
+    /// Next holiday starting after `now`
+    pub fn next(&self, now: DateTime<Utc>) -> Option<Occurrence> {
+        self.holidays
+            .iter()
+            .flat_map(|h| h.occurrences(now))
+            .filter(|o| o.start > now)
+            .min_by_key(|o| o.start)
+    }
+
+    /// Next start or end of a holiday after `now`
+    pub fn next_transition(&self, now: DateTime<Utc>) -> Option<Transition> {
+        let end = self.current(now).map(Transition::End);
+        let start = self.next(now).map(Transition::Start);
+        match (start, end) {
+            // a holiday starting when the current one ends keeps the groups asleep
+            (Some(start), Some(end)) if end.time() < start.time() => Some(end),
+            (start, end) => start.or(end),
+        }
+    }
+}
+
+pub fn calendar() -> Calendar {
+    CALENDAR.get().unwrap_or_default()
+}
+
+/// Holiday started since the last call, `None` if there is none or if it was already returned
+pub fn entered(now: DateTime<Utc>) -> Option<Occurrence> {
+    let current = calendar().current(now);
+    let previous = ENTERED.get().flatten();
+    ENTERED.set(current.clone());
+    current.filter(|c| previous.as_ref() != Some(c))
+}
+
+/// A parsed calendar, with the warnings of its lines
+type ParsedCalendar = (Calendar, Vec<(usize, String)>);
+
+/// Time zone of the TZID parameter of a time, `Err` with the TZID if it is unknown
+fn time_zone(params: &[&str]) -> Result<Option<Tz>, String> {
+    let tzid = params.iter().find_map(|p| {
+        p.split_once('=')
+            .filter(|(key, _)| key.eq_ignore_ascii_case("TZID"))
+            .map(|(_, tzid)| tzid.trim_matches('"'))
+    });
+    tzid.map(|tzid| tzid.parse().map_err(|_| tzid.to_string()))
+        .transpose()
+}
+
+/// An event being parsed
+#[derive(Default)]
+struct Event {
+    /// Line of BEGIN:VEVENT
+    line: usize,
+    summary: Option<String>,
+    start: Option<Time>,
+    end: Option<Time>,
+    yearly: bool,
+}
+
+impl Event {
+    /// The holiday of the event, `None` if it lasts no time
+    fn build(self, timezone: Timezone) -> Result<Option<Holiday>, String> {
+        let start = self.start.ok_or("event without DTSTART")?;
+        let end = match (self.end, &start) {
+            (Some(end), _) => end,
+            // an all-day event without end lasts one day
+            (None, Time::Date(date)) => Time::Date(
+                date.checked_add_days(Days::new(1))
+                    .ok_or("invalid DTSTART")?,
+            ),
+            (None, Time::DateTime(_)) => start.clone(),
+        };
+        let summary = self.summary.unwrap_or_default();
+        let (start, end) = (start.utc(timezone)?, end.utc(timezone)?);
+        if end <= start {
+            debug!("Ignoring holiday '{summary}' lasting no time");
+            return Ok(None);
+        }
+
+        Ok(Some(Holiday {
+            summary,
+            start,
+            end,
+            yearly: self.yearly,
+        }))
+    }
+}
+
+#[derive(Clone)]
+enum Time {
+    /// A whole day, starting at midnight in the configured timezone
+    Date(NaiveDate),
+    DateTime(DateTime<Utc>),
+}
+
+impl Time {
+    /// Parse a date or a time, local times being in `zone`
+    fn parse(params: &[&str], value: &str, zone: Tz) -> Result<Time, String> {
+        let invalid = |e| format!("invalid date '{value}' : {e}");
+
+        if params.iter().any(|p| p.eq_ignore_ascii_case("VALUE=DATE")) || value.len() == 8 {
+            return NaiveDate::parse_from_str(value, "%Y%m%d")
+                .map(Time::Date)
+                .map_err(invalid);
+        }
+        if let Some(utc) = value.strip_suffix('Z') {
+            return NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S")
+                .map(|t| Time::DateTime(t.and_utc()))
+                .map_err(invalid);
+        }
+        // the first of the repeated times when the clocks go back
+        NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+            .map_err(invalid)?
+            .and_local_timezone(zone)
+            .earliest()
+            .map(|t| Time::DateTime(t.to_utc()))
+            .ok_or(format!("invalid local time '{value}'"))
+    }
+
+    fn utc(&self, timezone: Timezone) -> Result<DateTime<Utc>, String> {
+        match self {
+            Time::Date(date) => date
+                .and_hms_opt(0, 0, 0)
+                .and_then(|t| t.and_local_timezone(timezone.0).earliest())
+                .map(|t| t.to_utc())
+                .ok_or(format!("invalid date '{date}'")),
+            Time::DateTime(time) => Ok(*time),
+        }
+    }
+}
+
+/// Content lines with their line number, long lines folded on several lines being joined
+fn unfold(raw: &str) -> Vec<(usize, String)> {
+    let mut lines: Vec<(usize, String)> = Vec::new();
+    for (i, line) in raw.lines().enumerate() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some((_, last))) => last.push_str(continuation),
+            _ if line.trim().is_empty() => {}
+            _ => lines.push((i + 1, line.to_string())),
+        }
+    }
+    lines
+}
+
+/// Text value without its escaping
+fn unescape(value: &str) -> String {
+    let mut text = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n' | 'N') => text.push('\n'),
+                Some(c) => text.push(c),
+                None => {}
+            },
+            c => text.push(c),
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paris() -> Timezone {
+        "Europe/Paris".parse().unwrap()
+    }
+
+    fn utc(time: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(time).unwrap().to_utc()
+    }
+
+    fn calendar(events: &str) -> ParsedCalendar {
+        Calendar::parse(
+            &format!("BEGIN:VCALENDAR\nVERSION:2.0\n{events}END:VCALENDAR\n"),
+            paris(),
+        )
+        .unwrap()
+    }
+
+    fn holiday(summary: &str, start: &str, end: &str, yearly: bool) -> Holiday {
+        Holiday {
+            summary: summary.to_string(),
+            start: utc(start),
+            end: utc(end),
+            yearly,
+        }
+    }
+
+    #[test]
+    fn unfold_lines() {
+        let raw = "BEGIN:VEVENT\r\nSUMMARY:A very\r\n  long\r\n\t summary\r\n\r\nEND:VEVENT\r\n";
+        assert_eq!(
+            unfold(raw),
+            [
+                (1, "BEGIN:VEVENT".to_string()),
+                (2, "SUMMARY:A very long summary".to_string()),
+                (6, "END:VEVENT".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_dates_and_times() {
+        let (calendar, warnings) = calendar(
+            "BEGIN:VEVENT\n\
+             SUMMARY:Christmas\n\
+             DTSTART;VALUE=DATE:20261225\n\
+             DTEND;VALUE=DATE:20261227\n\
+             END:VEVENT\n\
+             BEGIN:VEVENT\n\
+             SUMMARY:Maintenance\\, UTC\n\
+             DTSTART:20260301T100000Z\n\
+             DTEND:20260301T120000Z\n\
+             END:VEVENT\n\
+             BEGIN:VEVENT\n\
+             SUMMARY:Local\n\
+             DTSTART:20260701T100000\n\
+             DTEND;TZID=America/New_York:20260701T100000\n\
+             END:VEVENT\n",
+        );
+
+        assert!(warnings.is_empty());
+        assert_eq!(
+            calendar.holidays,
+            [
+                // midnight in Paris, in winter
+                holiday(
+                    "Christmas",
+                    "2026-12-24T23:00:00Z",
+                    "2026-12-26T23:00:00Z",
+                    false
+                ),
+                holiday(
+                    "Maintenance, UTC",
+                    "2026-03-01T10:00:00Z",
+                    "2026-03-01T12:00:00Z",
+                    false
+                ),
+                // Paris and New York, in summer
+                holiday(
+                    "Local",
+                    "2026-07-01T08:00:00Z",
+                    "2026-07-01T14:00:00Z",
+                    false
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_time_zone_is_reported() {
+        let (calendar, warnings) = calendar(
+            "BEGIN:VEVENT\n\
+             DTSTART;TZID=Europe/Nowhere:20260701T100000\n\
+             DTEND:20260701T120000\n\
+             END:VEVENT\n",
+        );
+
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].0, 4);
+        assert_eq!(calendar.holidays[0].start, utc("2026-07-01T08:00:00Z"));
+    }
+
+    #[test]
+    fn missing_end() {
+        let (calendar, _) = calendar(
+            "BEGIN:VEVENT\n\
+             SUMMARY:All day\n\
+             DTSTART;VALUE=DATE:20260501\n\
+             END:VEVENT\n\
+             BEGIN:VEVENT\n\
+             SUMMARY:No time\n\
+             DTSTART:20260501T100000Z\n\
+             END:VEVENT\n",
+        );
+
+        // an all-day event lasts one day, an event at a time lasts no time
+        assert_eq!(
+            calendar.holidays,
+            [holiday(
+                "All day",
+                "2026-04-30T22:00:00Z",
+                "2026-05-01T22:00:00Z",
+                false
+            )]
+        );
+    }
+
+    #[test]
+    fn yearly_events() {
+        let (calendar, warnings) = calendar(
+            "BEGIN:VEVENT\n\
+             SUMMARY:Christmas\n\
+             DTSTART;VALUE=DATE:20201225\n\
+             RRULE:FREQ=YEARLY\n\
+             END:VEVENT\n",
+        );
+
+        assert!(warnings.is_empty());
+        let christmas = calendar.current(utc("2026-12-25T12:00:00Z")).unwrap();
+        assert_eq!(christmas.start, utc("2026-12-24T23:00:00Z"));
+        assert_eq!(christmas.end, utc("2026-12-25T23:00:00Z"));
+        assert_eq!(
+            calendar.next(utc("2026-12-26T12:00:00Z")).unwrap().start,
+            utc("2027-12-24T23:00:00Z")
+        );
+    }
+
+    #[test]
+    fn unsupported_recurrence_is_reported() {
+        let (calendar, warnings) = calendar(
+            "BEGIN:VEVENT\n\
+             SUMMARY:Weekly\n\
+             DTSTART;VALUE=DATE:20260105\n\
+             RRULE:FREQ=WEEKLY;BYDAY=MO\n\
+             END:VEVENT\n",
+        );
+
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].0, 6);
+        assert!(warnings[0].1.contains("FREQ=WEEKLY;BYDAY=MO"));
+
+        // only the first occurrence is used
+        assert!(calendar.current(utc("2026-01-05T12:00:00Z")).is_some());
+        assert!(calendar.current(utc("2026-01-12T12:00:00Z")).is_none());
+        assert!(calendar.next(utc("2026-01-06T12:00:00Z")).is_none());
+    }
+
+    #[test]
+    fn invalid_events_are_rejected_with_their_line() {
+        let raw = "BEGIN:VCALENDAR\nBEGIN:VEVENT\nDTSTART:2026-01-01\nEND:VEVENT\nEND:VCALENDAR\n";
+        assert_eq!(Calendar::parse(raw, paris()).unwrap_err().0, 3);
+
+        let raw = "BEGIN:VCALENDAR\nBEGIN:VEVENT\nSUMMARY:No start\nEND:VEVENT\nEND:VCALENDAR\n";
+        assert_eq!(Calendar::parse(raw, paris()).unwrap_err().0, 2);
+    }
+
+    #[test]
+    fn transitions_at_boundaries() {
+        let (calendar, _) = calendar(
+            "BEGIN:VEVENT\n\
+             SUMMARY:First\n\
+             DTSTART:20260801T000000Z\n\
+             DTEND:20260802T000000Z\n\
+             END:VEVENT\n\
+             BEGIN:VEVENT\n\
+             SUMMARY:Second\n\
+             DTSTART:20260802T000000Z\n\
+             DTEND:20260803T000000Z\n\
+             END:VEVENT\n",
+        );
+        let first = Occurrence {
+            summary: "First".to_string(),
+            start: utc("2026-08-01T00:00:00Z"),
+            end: utc("2026-08-02T00:00:00Z"),
+        };
+        let second = Occurrence {
+            summary: "Second".to_string(),
+            start: utc("2026-08-02T00:00:00Z"),
+            end: utc("2026-08-03T00:00:00Z"),
+        };
+
+        // the start is included, the end excluded
+        let before = utc("2026-07-31T23:59:59Z");
+        assert_eq!(calendar.current(before), None);
+        assert_eq!(
+            calendar.next_transition(before),
+            Some(Transition::Start(first.clone()))
+        );
+        assert_eq!(calendar.current(first.start), Some(first.clone()));
+        assert_eq!(
+            calendar.next_transition(first.start),
+            // the groups stay asleep from a holiday to the next one
+            Some(Transition::Start(second.clone()))
+        );
+        assert_eq!(calendar.current(second.start), Some(second.clone()));
+        assert_eq!(
+            calendar.next_transition(second.start),
+            Some(Transition::End(second.clone()))
+        );
+        assert_eq!(calendar.current(second.end), None);
+        assert_eq!(calendar.next_transition(second.end), None);
+    }
+}
@@ -0,0 +1,17 @@
+//! Clock of the state machine : the system clock, or a virtual one when simulating recorded metrics.
+
+use std::time::Instant;
+
+use crate::core::swappable::Swappable;
+
+/// Current time of the virtual clock, if any
+static VIRTUAL_NOW: Swappable<Instant> = Swappable::new();
+
+pub fn now() -> Instant {
+    VIRTUAL_NOW.get().unwrap_or_else(Instant::now)
+}
+
+/// Use a virtual clock, set to `now` until the next call
+pub fn set_virtual(now: Instant) {
+    VIRTUAL_NOW.set(now);
+}
@@ -0,0 +1,41 @@
+use core::fmt;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Name of the group used when all resources are managed together
+pub const CLUSTER_GROUP: &str = "cluster";
+
+/// How resources without a `kubesleeper/group` annotation are grouped.
+///
+/// Each group has its own Awake/Asleep state: traffic received by a resource
+/// only wakes the resources of its group.
+#[derive(Eq, PartialEq, Debug, Serialize, Deserialize, Clone, Copy, Default, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupBy {
+    /// All resources belong to the same group (the whole cluster sleeps and wakes at once)
+    #[default]
+    Cluster,
+
+    /// Resources are grouped by namespace
+    Namespace,
+}
+
+impl GroupBy {
+    /// Compute the group name of a resource.
+    ///
+    /// The `kubesleeper/group` annotation always takes precedence over the grouping strategy.
+    pub fn group_of(&self, namespace: &str, group_annotation: Option<&str>) -> String {
+        match (group_annotation, self) {
+            (Some(group), _) => group.to_string(),
+            (None, GroupBy::Cluster) => CLUSTER_GROUP.to_string(),
+            (None, GroupBy::Namespace) => namespace.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for GroupBy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", format!("{self:?}").to_lowercase())
+    }
+}
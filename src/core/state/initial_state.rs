@@ -0,0 +1,17 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// State of the groups when kubesleeper starts
+#[derive(Eq, PartialEq, Debug, Serialize, Deserialize, Clone, Copy, Default, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum InitialState {
+    /// All groups are assumed awake, as if they just received activity
+    #[default]
+    Awake,
+
+    /// All groups are set asleep, until they receive activity
+    Asleep,
+
+    /// The state of each group is detected from its resources
+    Detect,
+}
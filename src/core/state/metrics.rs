@@ -0,0 +1,193 @@
+use std::collections::{HashMap, HashSet};
+
+use tracing::debug;
+
+/// A monotonic request counter of a single ingress pod
+#[derive(Eq, PartialEq, Debug, Default, Clone, Copy)]
+pub struct Counter {
+    /// Last scraped value of the counter
+    pub total: u64,
+
+    /// Number of requests received since the previous scrape
+    pub delta: u64,
+}
+
+impl Counter {
+    /// Compute the counter following `previous` for a newly scraped `total`
+    pub fn next(previous: Option<&Counter>, total: u64) -> Counter {
+        let delta = match previous {
+            // A new counter of a known ingress pod is only exported once it has received
+            // at least 1 request, all its requests are new
+            None => total,
+            Some(previous) if total >= previous.total => total - previous.total,
+            // The counter went down : it has been reset (ingress pod restart),
+            // all requests since the reset are new
+            Some(previous) => {
+                debug!("Counter reset detected ({} > {total})", previous.total);
+                total
+            }
+        };
+        Counter { total, delta }
+    }
+}
+
+/// Request counters of each ingress service, by ingress pod uid
+#[derive(Debug, Default)]
+pub struct Metrics(pub HashMap<String, HashMap<String, Counter>>);
+
+impl Metrics {
+    /// Update counters from a new scrape, computing the deltas since the previous one
+    ///
+    /// Counters of ingress pods missing from the previous scrape (new pods, or all pods on the
+    /// first scrape) are a baseline : their requests may have been received at any time.
+    /// Services and ingress pods missing from the scrape are forgotten
+    pub fn update(
+        &mut self,
+        // HashMap<ServiceId, HashMap<Ingress Pod Uid, nb of connections received>>
+        scrape: &HashMap<String, HashMap<String, u64>>,
+    ) {
+        let known_pods: HashSet<&String> = self.0.values().flat_map(|c| c.keys()).collect();
+        self.0 = scrape
+            .iter()
+            .map(|(service_id, totals)| {
+                let previous = self.0.get(service_id);
+                let counters = totals
+                    .iter()
+                    .map(|(ingress_pod_uid, total)| {
+                        let counter = match known_pods.contains(ingress_pod_uid) {
+                            true => {
+                                let previous = previous.and_then(|p| p.get(ingress_pod_uid));
+                                Counter::next(previous, *total)
+                            }
+                            false => {
+                                debug!("New ingress pod '{ingress_pod_uid}', its counters are a baseline");
+                                Counter {
+                                    total: *total,
+                                    delta: 0,
+                                }
+                            }
+                        };
+                        (ingress_pod_uid.clone(), counter)
+                    })
+                    .collect();
+                (service_id.clone(), counters)
+            })
+            .collect();
+    }
+
+    /// Number of requests received by a service (on all ingress pods) since the previous scrape
+    pub fn delta(&self, service_id: &str) -> u64 {
+        self.0
+            .get(service_id)
+            .map(|counters| counters.values().map(|c| c.delta).sum())
+            .unwrap_or_default()
+    }
+
+    pub fn service_ids(&self) -> impl Iterator<Item = &String> {
+        self.0.keys()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scrape(totals: &[(&str, &str, u64)]) -> HashMap<String, HashMap<String, u64>> {
+        let mut scrape: HashMap<String, HashMap<String, u64>> = HashMap::new();
+        for (service_id, pod_uid, total) in totals {
+            scrape
+                .entry(service_id.to_string())
+                .or_default()
+                .insert(pod_uid.to_string(), *total);
+        }
+        scrape
+    }
+
+    #[test]
+    fn first_scrape_is_a_baseline() {
+        let mut metrics = Metrics::default();
+        metrics.update(&scrape(&[("web", "pod-a", 42), ("api", "pod-a", 7)]));
+
+        assert_eq!(metrics.delta("web"), 0);
+        assert_eq!(metrics.delta("api"), 0);
+    }
+
+    #[test]
+    fn steady_counter_has_no_delta() {
+        let mut metrics = Metrics::default();
+        metrics.update(&scrape(&[("web", "pod-a", 42)]));
+        metrics.update(&scrape(&[("web", "pod-a", 42)]));
+
+        assert_eq!(metrics.delta("web"), 0);
+    }
+
+    #[test]
+    fn increment_is_the_delta() {
+        let mut metrics = Metrics::default();
+        metrics.update(&scrape(&[("web", "pod-a", 42), ("web", "pod-b", 10)]));
+        metrics.update(&scrape(&[("web", "pod-a", 45), ("web", "pod-b", 12)]));
+
+        assert_eq!(metrics.delta("web"), 5);
+
+        // a service of a known pod receiving its first requests
+        metrics.update(&scrape(&[
+            ("web", "pod-a", 45),
+            ("web", "pod-b", 12),
+            ("api", "pod-a", 3),
+        ]));
+        assert_eq!(metrics.delta("web"), 0);
+        assert_eq!(metrics.delta("api"), 3);
+    }
+
+    #[test]
+    fn reset_counts_the_new_total() {
+        let mut metrics = Metrics::default();
+        metrics.update(&scrape(&[("web", "pod-a", 42)]));
+        metrics.update(&scrape(&[("web", "pod-a", 4)]));
+
+        assert_eq!(metrics.delta("web"), 4);
+        assert_eq!(
+            Counter::next(
+                Some(&Counter {
+                    total: 42,
+                    delta: 0
+                }),
+                0
+            ),
+            Counter { total: 0, delta: 0 }
+        );
+    }
+
+    #[test]
+    fn new_ingress_pod_is_not_activity() {
+        let mut metrics = Metrics::default();
+        metrics.update(&scrape(&[("web", "pod-a", 42)]));
+        metrics.update(&scrape(&[("web", "pod-a", 42), ("web", "pod-b", 1000)]));
+
+        assert_eq!(metrics.delta("web"), 0);
+
+        // its later requests are activity
+        metrics.update(&scrape(&[("web", "pod-a", 42), ("web", "pod-b", 1002)]));
+        assert_eq!(metrics.delta("web"), 2);
+    }
+
+    #[test]
+    fn vanished_pods_and_services_are_forgotten() {
+        let mut metrics = Metrics::default();
+        metrics.update(&scrape(&[
+            ("web", "pod-a", 42),
+            ("web", "pod-b", 10),
+            ("api", "pod-a", 7),
+        ]));
+        metrics.update(&scrape(&[("web", "pod-a", 43)]));
+
+        assert_eq!(metrics.delta("web"), 1);
+        assert_eq!(metrics.delta("api"), 0);
+        assert_eq!(metrics.service_ids().collect::<Vec<_>>(), ["web"]);
+        assert_eq!(metrics.0["web"].keys().collect::<Vec<_>>(), ["pod-a"]);
+
+        // a service coming back on a known pod counts all its requests
+        metrics.update(&scrape(&[("web", "pod-a", 43), ("api", "pod-a", 9)]));
+        assert_eq!(metrics.delta("api"), 9);
+    }
+}
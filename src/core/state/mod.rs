@@ -1,6 +1,14 @@
-use crate::core::resource::error;
+use crate::core::{ingress::error::IngressError, resource::error};
 
+pub mod calendar;
+pub mod clock;
+pub mod group;
+pub mod initial_state;
+pub mod metrics;
 pub mod notification;
+pub mod persistence;
+pub mod schedule;
+pub mod sleepiness;
 pub mod state;
 pub mod state_kind;
 
@@ -12,6 +20,9 @@ pub enum StateError {
     #[error(transparent)]
     Controller(#[from] error::Resource),
 
+    #[error(transparent)]
+    Ingress(#[from] IngressError),
+
     #[error("Invalid State Kind: {0}")]
     InvalidStateKindError(String),
 }
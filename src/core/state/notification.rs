@@ -1,6 +1,10 @@
+use serde::{Deserialize, Serialize};
 use std::time::Instant;
 
-#[derive(Eq, PartialEq, Debug)]
+use crate::core::state::clock;
+
+#[derive(Eq, PartialEq, Debug, Serialize, Deserialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
 pub enum NotificationKind {
     Activity,
     NoActivity,
@@ -16,7 +20,7 @@ impl Notification {
     pub fn new(kind: NotificationKind) -> Notification {
         Notification {
             kind,
-            timestamp: Instant::now(),
+            timestamp: clock::now(),
         }
     }
 }
@@ -0,0 +1,195 @@
+//! Persistence of the controller state in a ConfigMap, so a restarted kubesleeper keeps the
+//! sleepiness countdown of each group and doesn't count the requests of the last metrics
+//! snapshot as new activity.
+
+use k8s_openapi::{
+    api::core::v1::ConfigMap,
+    apimachinery::pkg::apis::meta::v1::ObjectMeta,
+    chrono::{self, DateTime, Utc},
+};
+use kube::{
+    Api, Client,
+    api::{Patch, PatchParams},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::Instant,
+};
+use tracing::{debug, warn};
+
+use crate::core::{
+    resource::{constantes::KUBESLLEPER_APP_NAME, dry_run, error},
+    state::{
+        metrics::{Counter, Metrics},
+        notification::{Notification, NotificationKind},
+        state::{GroupState, State},
+        state_kind::StateKind,
+    },
+};
+
+/// Name of the ConfigMap storing the state, in the namespace of kubesleeper
+pub const STATE_CONFIG_MAP_NAME: &str = "kubesleeper-state";
+
+/// Key of the state in the ConfigMap data
+const STATE_KEY: &str = "state.json";
+
+/// The controller state, with wall-clock timestamps
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Snapshot {
+    /// State of each group, by group name
+    pub groups: BTreeMap<String, GroupSnapshot>,
+
+    /// Last scraped request counters, by ingress service then ingress pod uid
+    pub metrics: BTreeMap<String, BTreeMap<String, u64>>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GroupSnapshot {
+    pub kind: StateKind,
+    pub since: NotificationKind,
+    pub since_time: DateTime<Utc>,
+
+    #[serde(default)]
+    pub last_transition: Option<DateTime<Utc>>,
+
+    /// Number of transitions suppressed by the minimum awake duration or the cooldown
+    #[serde(default)]
+    pub suppressed: u64,
+}
+
+impl Snapshot {
+    pub fn of(state: &State) -> Snapshot {
+        let groups = state
+            .groups
+            .iter()
+            .map(|(group, state)| {
+                let snapshot = GroupSnapshot {
+                    kind: state.kind,
+                    since: state.since.kind,
+                    since_time: to_wall_clock(state.since.timestamp),
+                    last_transition: state.last_transition.map(to_wall_clock),
+                    suppressed: state.suppressed,
+                };
+                (group.clone(), snapshot)
+            })
+            .collect();
+        let metrics = state
+            .metrics
+            .0
+            .iter()
+            .map(|(service_id, counters)| {
+                let totals = counters
+                    .iter()
+                    .map(|(pod_uid, counter)| (pod_uid.clone(), counter.total))
+                    .collect();
+                (service_id.clone(), totals)
+            })
+            .collect();
+        Snapshot { groups, metrics }
+    }
+
+    /// Groups states and metrics of the snapshot
+    pub fn restore(self) -> (HashMap<String, GroupState>, Metrics) {
+        let groups = self
+            .groups
+            .into_iter()
+            .map(|(group, snapshot)| {
+                let state = GroupState {
+                    kind: snapshot.kind,
+                    since: Notification {
+                        kind: snapshot.since,
+                        timestamp: to_instant(snapshot.since_time),
+                    },
+                    last_transition: snapshot.last_transition.map(to_instant),
+                    suppressed: snapshot.suppressed,
+                    suppressing: None,
+                };
+                (group, state)
+            })
+            .collect();
+        // requests of the snapshot have already been counted
+        let metrics = self
+            .metrics
+            .into_iter()
+            .map(|(service_id, totals)| {
+                let counters = totals
+                    .into_iter()
+                    .map(|(pod_uid, total)| (pod_uid, Counter { total, delta: 0 }))
+                    .collect();
+                (service_id, counters)
+            })
+            .collect();
+        (groups, Metrics(metrics))
+    }
+}
+
+fn to_wall_clock(instant: Instant) -> DateTime<Utc> {
+    let elapsed = chrono::Duration::from_std(instant.elapsed()).unwrap_or_default();
+    Utc::now() - elapsed
+}
+
+fn to_instant(time: DateTime<Utc>) -> Instant {
+    // a time in the future is now
+    let elapsed = (Utc::now() - time).to_std().unwrap_or_default();
+    Instant::now().checked_sub(elapsed).unwrap_or_else(|| {
+        debug!("Time {time} is before the start of the monotonic clock, using now");
+        Instant::now()
+    })
+}
+
+async fn get_k8s_api() -> Result<Api<ConfigMap>, error::Resource> {
+    let client = Client::try_default().await?;
+    Ok(Api::default_namespaced(client))
+}
+
+/// Write `snapshot` to the state ConfigMap, creating it if needed
+pub async fn save(snapshot: &Snapshot) -> Result<(), error::Resource> {
+    // a later run would restore groups which have never been set asleep
+    if dry_run::is_enabled() {
+        debug!("Dry run : state not saved");
+        return Ok(());
+    }
+
+    let config_map = ConfigMap {
+        metadata: ObjectMeta {
+            name: Some(STATE_CONFIG_MAP_NAME.to_string()),
+            ..Default::default()
+        },
+        data: Some(BTreeMap::from([(
+            STATE_KEY.to_string(),
+            serde_json::to_string(snapshot)?,
+        )])),
+        ..Default::default()
+    };
+
+    get_k8s_api()
+        .await?
+        .patch(
+            STATE_CONFIG_MAP_NAME,
+            &PatchParams::apply(KUBESLLEPER_APP_NAME).force(),
+            &Patch::Apply(&config_map),
+        )
+        .await?;
+    debug!("State saved to ConfigMap '{STATE_CONFIG_MAP_NAME}'");
+    Ok(())
+}
+
+/// Read the snapshot of the state ConfigMap, `None` if there is none or if it is invalid
+pub async fn load() -> Result<Option<Snapshot>, error::Resource> {
+    let Some(config_map) = get_k8s_api().await?.get_opt(STATE_CONFIG_MAP_NAME).await? else {
+        debug!("No ConfigMap '{STATE_CONFIG_MAP_NAME}' : no state to restore");
+        return Ok(None);
+    };
+
+    let Some(raw) = config_map.data.and_then(|mut data| data.remove(STATE_KEY)) else {
+        return Ok(None);
+    };
+    match serde_json::from_str(&raw) {
+        Ok(snapshot) => Ok(Some(snapshot)),
+        Err(e) => {
+            warn!("Ignoring invalid state of ConfigMap '{STATE_CONFIG_MAP_NAME}' : {e}");
+            Ok(None)
+        }
+    }
+}
@@ -0,0 +1,305 @@
+//! Time-based schedules ("office hours") setting all groups asleep or awake at given times,
+//! on top of the activity of the groups.
+
+use chrono_tz::Tz;
+use core::fmt;
+use croner::Cron;
+use k8s_openapi::chrono::{DateTime, Days, FixedOffset, Utc};
+use schemars::{JsonSchema, Schema, SchemaGenerator, json_schema};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{borrow::Cow, str::FromStr};
+
+use crate::core::{state::state_kind::StateKind, swappable::Swappable};
+
+pub static SCHEDULES: Swappable<Vec<Schedule>> = Swappable::new();
+
+pub static TIMEZONE: Swappable<Timezone> = Swappable::new();
+
+/// Schedule holding the state of all groups, until the next schedule
+static HOLD: Swappable<Option<Schedule>> = Swappable::new();
+
+/// Time zone of the schedules : an IANA time zone name (like 'Europe/Paris'), following its
+/// daylight saving time
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Timezone(pub Tz);
+
+impl Default for Timezone {
+    fn default() -> Self {
+        Timezone(Tz::UTC)
+    }
+}
+
+impl FromStr for Timezone {
+    type Err = String;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        if raw.eq_ignore_ascii_case("utc") || raw == "Z" {
+            return Ok(Timezone::default());
+        }
+        // whole hour offsets are the fixed 'Etc/GMT' zones, which have an inverted sign
+        if let Ok(offset) = raw.parse::<FixedOffset>() {
+            let seconds = offset.local_minus_utc();
+            return match (seconds % 3600, seconds / 3600) {
+                (0, 0) => Ok(Timezone::default()),
+                (0, hours) => format!("Etc/GMT{:+}", -hours)
+                    .parse()
+                    .map(Timezone)
+                    .map_err(|e| format!("invalid timezone '{raw}' : {e}")),
+                _ => Err(format!(
+                    "invalid timezone '{raw}' : only whole hour offsets are supported, use a time zone name like 'Asia/Kolkata'"
+                )),
+            };
+        }
+        raw.parse::<Tz>().map(Timezone).map_err(|e| {
+            format!(
+                "invalid timezone '{raw}' : {e} (expected a time zone name like 'Europe/Paris')"
+            )
+        })
+    }
+}
+
+impl fmt::Display for Timezone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.name())
+    }
+}
+
+impl Serialize for Timezone {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Timezone {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl JsonSchema for Timezone {
+    fn schema_name() -> Cow<'static, str> {
+        "Timezone".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "string",
+            "description": "An IANA time zone name (like 'Europe/Paris'), 'UTC' or a whole hour offset from UTC (like '+01:00')"
+        })
+    }
+}
+
+/// A cron expression with seconds, like '0 0 20 * * Mon-Fri' (every weekday at 20:00)
+#[derive(Debug, Clone)]
+pub struct CronPattern {
+    raw: String,
+    cron: Cron,
+}
+
+impl CronPattern {
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+}
+
+impl PartialEq for CronPattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.raw == other.raw
+    }
+}
+
+impl Eq for CronPattern {}
+
+impl FromStr for CronPattern {
+    type Err = String;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        // same syntax as the jobs of the scheduler
+        let cron = Cron::new(raw)
+            .with_seconds_required()
+            .with_dom_and_dow()
+            .parse()
+            .map_err(|e| format!("invalid cron expression '{raw}' : {e}"))?;
+        Ok(CronPattern {
+            raw: raw.to_string(),
+            cron,
+        })
+    }
+}
+
+impl Serialize for CronPattern {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.raw)
+    }
+}
+
+impl<'de> Deserialize<'de> for CronPattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl JsonSchema for CronPattern {
+    fn schema_name() -> Cow<'static, str> {
+        "CronPattern".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "string",
+            "description": "A cron expression with seconds, like '0 0 20 * * Mon-Fri' (every weekday at 20:00)"
+        })
+    }
+}
+
+/// Set all groups to a state at the times of a cron expression
+#[derive(Serialize, Debug, Deserialize, Clone, Eq, PartialEq, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Schedule {
+    /// Times of the schedule, in the configured timezone
+    pub cron: CronPattern,
+
+    /// State all groups are set to
+    pub state: StateKind,
+
+    /// Keep the state until the next schedule : traffic doesn't wake asleep groups,
+    /// inactivity doesn't set awake groups asleep
+    #[serde(default)]
+    pub hold: bool,
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "'{}' ({}", self.cron.as_str(), self.state)?;
+        if self.hold {
+            write!(f, ", held")?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl Schedule {
+    /// Last time of the schedule at or before `now`, looking back up to a year
+    fn previous(&self, now: DateTime<Tz>) -> Option<DateTime<Tz>> {
+        // a week is enough for most schedules, sparse ones are searched over a year
+        [7, 366].into_iter().find_map(|days| {
+            let start = now.checked_sub_days(Days::new(days))?;
+            self.cron
+                .cron
+                .iter_from(start)
+                .take_while(|time| *time <= now)
+                .last()
+        })
+    }
+
+    /// Next time of the schedule after `now`
+    pub fn next(&self, now: DateTime<Tz>) -> Option<DateTime<Tz>> {
+        self.cron.cron.find_next_occurrence(&now, false).ok()
+    }
+}
+
+pub fn timezone() -> Timezone {
+    TIMEZONE.get().unwrap_or_default()
+}
+
+pub fn schedules() -> Vec<Schedule> {
+    SCHEDULES.get().unwrap_or_default()
+}
+
+/// Schedule of `schedules` which ran last, at or before `now`
+pub fn current(
+    schedules: &[Schedule],
+    timezone: Timezone,
+    now: DateTime<Utc>,
+) -> Option<&Schedule> {
+    let now = now.with_timezone(&timezone.0);
+    schedules
+        .iter()
+        .filter_map(|s| Some((s.previous(now)?, s)))
+        .max_by_key(|(time, _)| *time)
+        .map(|(_, s)| s)
+}
+
+/// State held by the last schedule, if it holds its state
+pub fn hold() -> Option<StateKind> {
+    HOLD.get().flatten().map(|s| s.state)
+}
+
+/// Hold the state of `schedule` until the next one, if it holds its state
+pub fn set_hold(schedule: &Schedule) {
+    HOLD.set(schedule.hold.then(|| schedule.clone()));
+}
+
+pub fn clear_hold() {
+    HOLD.set(None);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schedule(cron: &str, state: StateKind) -> Schedule {
+        Schedule {
+            cron: cron.parse().unwrap(),
+            state,
+            hold: false,
+        }
+    }
+
+    fn utc(time: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(time).unwrap().to_utc()
+    }
+
+    #[test]
+    fn parse_timezone() {
+        assert_eq!("UTC".parse(), Ok(Timezone(Tz::UTC)));
+        assert_eq!("Z".parse(), Ok(Timezone(Tz::UTC)));
+        assert_eq!("+00:00".parse(), Ok(Timezone(Tz::UTC)));
+        assert_eq!("Europe/Paris".parse(), Ok(Timezone(Tz::Europe__Paris)));
+        assert_eq!("+01:00".parse(), Ok(Timezone(Tz::Etc__GMTMinus1)));
+        assert_eq!("-05:00".parse(), Ok(Timezone(Tz::Etc__GMTPlus5)));
+        assert!("+05:30".parse::<Timezone>().is_err());
+        assert!("Europe/Nowhere".parse::<Timezone>().is_err());
+        assert_eq!(Timezone(Tz::Europe__Paris).to_string(), "Europe/Paris");
+    }
+
+    #[test]
+    fn schedules_follow_daylight_saving_time() {
+        let timezone: Timezone = "Europe/Paris".parse().unwrap();
+        let night = schedule("0 0 20 * * *", StateKind::Asleep);
+        let day = schedule("0 0 8 * * *", StateKind::Awake);
+        let schedules = [night.clone(), day.clone()];
+
+        // winter : UTC+1
+        let now = utc("2026-01-15T19:30:00Z");
+        assert_eq!(current(&schedules, timezone, now), Some(&night));
+        assert_eq!(
+            night
+                .next(now.with_timezone(&timezone.0))
+                .map(|t| t.to_utc()),
+            Some(utc("2026-01-16T19:00:00Z"))
+        );
+
+        // summer : UTC+2
+        let now = utc("2026-07-15T18:30:00Z");
+        assert_eq!(current(&schedules, timezone, now), Some(&night));
+        assert_eq!(
+            night
+                .next(now.with_timezone(&timezone.0))
+                .map(|t| t.to_utc()),
+            Some(utc("2026-07-16T18:00:00Z"))
+        );
+        assert_eq!(
+            current(&schedules, timezone, utc("2026-07-15T05:30:00Z")),
+            Some(&night)
+        );
+        assert_eq!(
+            current(&schedules, timezone, utc("2026-07-15T06:00:00Z")),
+            Some(&day)
+        );
+    }
+}
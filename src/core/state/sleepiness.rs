@@ -0,0 +1,73 @@
+use core::fmt;
+use serde::{Serialize, Serializer};
+use std::time::Duration;
+
+use crate::core::config::duration;
+
+/// Where the sleepiness duration of a group comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SleepinessSource {
+    /// The `controller.sleepiness_duration` configuration
+    Config,
+
+    /// The `kubesleeper/sleepiness-duration` annotation of a resource (like 'Deployment default/app')
+    Annotation(String),
+}
+
+impl fmt::Display for SleepinessSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SleepinessSource::Config => write!(f, "config"),
+            SleepinessSource::Annotation(resource) => write!(f, "annotation of {resource}"),
+        }
+    }
+}
+
+impl Serialize for SleepinessSource {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Sleepiness duration of a group, and where it comes from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Sleepiness {
+    #[serde(serialize_with = "duration::serialize")]
+    pub duration: Duration,
+    pub source: SleepinessSource,
+}
+
+impl Sleepiness {
+    pub fn config(duration: Duration) -> Self {
+        Sleepiness {
+            duration,
+            source: SleepinessSource::Config,
+        }
+    }
+
+    pub fn annotation(duration: Duration, resource: String) -> Self {
+        Sleepiness {
+            duration,
+            source: SleepinessSource::Annotation(resource),
+        }
+    }
+
+    /// Sleepiness of a group with both `self` and `other` resources.
+    ///
+    /// Annotations override the configured value, and the longest annotated duration is kept
+    /// so no resource of the group falls asleep before its own duration.
+    pub fn merge(self, other: Sleepiness) -> Sleepiness {
+        match (&self.source, &other.source) {
+            (SleepinessSource::Config, SleepinessSource::Annotation(_)) => other,
+            (SleepinessSource::Annotation(_), SleepinessSource::Config) => self,
+            _ if other.duration > self.duration => other,
+            _ => self,
+        }
+    }
+}
+
+impl fmt::Display for Sleepiness {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", duration::format(&self.duration), self.source)
+    }
+}
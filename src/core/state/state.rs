@@ -1,26 +1,44 @@
 use crate::core::{
-    ingress::traefik::Traefik,
-    resource::{TargetResource, deploy::Deploy, service::Service},
+    ingress::traefik::{Traefik, TraefikServiceResolver},
+    resource::{
+        TargetResource,
+        cron_job::CronJob,
+        deploy::Deploy,
+        hpa::Hpa,
+        redirection::{self, Redirection},
+        scalable::Scalable,
+        scaled_object::ScaledObject,
+        service::Service,
+        stateful_set::StatefulSet,
+    },
 };
 
 use crate::core::{
+    config,
     ingress::IngressType,
     state::{
-        StateError,
+        StateError, calendar, clock,
+        group::GroupBy,
+        initial_state::InitialState,
+        metrics::Metrics,
         notification::{Notification, NotificationKind},
+        persistence::{self, Snapshot},
+        schedule::{self, Schedule, Timezone},
+        sleepiness::Sleepiness,
         state_kind::StateKind,
     },
+    swappable::Swappable,
 };
 
+use k8s_openapi::chrono::Utc;
 use lazy_static::lazy_static;
-use std::num::NonZeroU32;
 use std::{
-    collections::HashMap,
-    sync::Mutex,
+    collections::{BTreeMap, BTreeSet, HashMap},
+    sync::{Arc, Mutex},
     time::{Duration, Instant},
 };
-use tokio_cron_scheduler::{Job, JobScheduler};
-use tracing::{debug, info, instrument};
+use tokio_cron_scheduler::{Job, JobScheduler, JobSchedulerError};
+use tracing::{debug, error, info, instrument, warn};
 use uuid::Uuid;
 
 // - - - - - - - - - - - - -
@@ -30,144 +48,699 @@ lazy_static! {
 
 //pub const ANNOTATION_STORE_STATE_KEY: &str = "store.state";
 
-pub static SLEEPINESS_DURATION: std::sync::OnceLock<Duration> = std::sync::OnceLock::new();
+pub static SLEEPINESS_DURATION: Swappable<Duration> = Swappable::new();
 
-#[derive(Debug)]
+pub static GROUP_BY: Swappable<GroupBy> = Swappable::new();
+
+pub static REFRESH_INTERVAL: Swappable<Duration> = Swappable::new();
+
+pub static MIN_AWAKE_DURATION: Swappable<Duration> = Swappable::new();
+
+pub static COOLDOWN: Swappable<Duration> = Swappable::new();
+
+#[derive(Debug, Default)]
 pub struct State {
+    /// State of each group of resources, by group name
+    pub groups: HashMap<String, GroupState>,
+    pub metrics: Metrics,
+
+    /// Sleepiness duration of each group, from the last refresh
+    pub sleepiness: BTreeMap<String, Sleepiness>,
+}
+
+/// Result of a notification of a group
+#[derive(Debug, Default)]
+pub struct Update {
+    /// State the resources of the group must be set to
+    pub action: Option<StateKind>,
+
+    /// Whether the state of the group changed
+    pub transition: bool,
+
+    /// Whether a transition started being suppressed
+    pub suppressed: bool,
+}
+
+#[derive(Debug)]
+pub struct GroupState {
     pub kind: StateKind,
     pub since: Notification,
-    pub metrics: HashMap<String, HashMap<String, u64>>,
+
+    /// Time of the last change of `kind`, `None` if it never changed
+    pub last_transition: Option<Instant>,
+
+    /// Number of sleeps suppressed by the minimum awake duration or the cooldown
+    pub suppressed: u64,
+
+    /// Transition being suppressed (only a sleep, wakes are never suppressed), counted once
+    /// until it happens or isn't wanted anymore
+    pub suppressing: Option<StateKind>,
 }
 
-impl State {
-    // TODO: review ingress suppression behavior ?
+fn group_by() -> GroupBy {
+    GROUP_BY.get().unwrap_or_default()
+}
 
-    fn create_notification_from_metrics(
-        // HashMap<ServiceId, HashMap<Ingress Pod Uid, nb of connections received>>
-        metrics_data: &HashMap<String, HashMap<String, u64>>,
-    ) -> Result<Notification, StateError> {
-        let state = STATE
-            .lock()
-            .map_err(|e| StateError::LockError(format!("{e:?}")))?;
+fn sleepiness_duration() -> Duration {
+    match SLEEPINESS_DURATION.get() {
+        Some(s) => s,
+        None => panic!("SLEEPINESS_DURATION should be set a this step"),
+    }
+}
 
-        for (service_id, metric) in metrics_data {
-            if let Some(stored_metric) = state.metrics.get(service_id) {
-                // Service already exists in the state,
-                // looking for update : is one of ingress pods has proceed at least 1 connection ?
-                for (ingress_pod_uid, total_connection) in metric {
-                    let stored_total_connection = stored_metric.get(ingress_pod_uid);
-                    if stored_total_connection.is_none() {
-                        // the ingress pod uid is not in the state, so its a new ingress pod,
-                        // to be registerd is must has received at least 1 connection, so there was activity
-                        debug!("Ingress pod with uid '{ingress_pod_uid}' is new > Activity ");
-                        return Ok(Notification::new(NotificationKind::Activity));
-                    }
+impl GroupState {
+    pub fn new(kind: StateKind, since: NotificationKind) -> Self {
+        GroupState {
+            kind,
+            since: Notification::new(since),
+            last_transition: None,
+            suppressed: 0,
+            suppressing: None,
+        }
+    }
 
-                    let nb_new_connection =
-                        stored_total_connection.map_or(0, |stored| stored - total_connection);
-                    if nb_new_connection > 0 {
-                        debug!(
-                            "Ingress pod with uid '{ingress_pod_uid}' has proceed {nb_new_connection} new connection > Activity "
-                        );
-                        return Ok(Notification::new(NotificationKind::Activity));
+    /// Why setting the group asleep at `now` must be suppressed, if it must
+    fn suppression(&self, now: Instant) -> Option<String> {
+        let elapsed = now.saturating_duration_since(self.last_transition?);
+        let min_awake_duration = MIN_AWAKE_DURATION.get().unwrap_or_default();
+        let cooldown = COOLDOWN.get().unwrap_or_default();
+
+        // the last transition of an awake group is its wake
+        if elapsed < min_awake_duration {
+            Some(format!(
+                "awake for {} of the minimum {}",
+                config::duration::format(&elapsed),
+                config::duration::format(&min_awake_duration)
+            ))
+        } else if elapsed < cooldown {
+            Some(format!(
+                "{} since the last transition, cooldown is {}",
+                config::duration::format(&elapsed),
+                config::duration::format(&cooldown)
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Set the group asleep, unless the minimum awake duration or the cooldown suppress it.
+    ///
+    /// Returns if the state changed.
+    fn fall_asleep(&mut self, now: Instant) -> bool {
+        if let Some(reason) = self.suppression(now) {
+            debug!("Transition to {} suppressed > {reason}", StateKind::Asleep);
+            self.suppressing = Some(StateKind::Asleep);
+            return false;
+        }
+        self.kind = StateKind::Asleep;
+        self.last_transition = Some(now);
+        true
+    }
+
+    /// Wake the group up : a wake caused by activity is never suppressed, so requests
+    /// don't wait for the cooldown
+    fn wake(&mut self, now: Instant) {
+        if self.kind == StateKind::Asleep {
+            self.kind = StateKind::Awake;
+            self.last_transition = Some(now);
+        }
+    }
+}
+
+impl State {
+    /// Set the state of all groups when kubesleeper starts : groups of the persisted state
+    /// are restored, the others are set from `initial_state`
+    pub async fn init(initial_state: InitialState) -> Result<(), StateError> {
+        let restored = State::restore().await?;
+
+        let groups: Vec<(String, GroupState)> = match initial_state {
+            // groups are created awake on their first notification
+            InitialState::Awake => return Ok(()),
+            InitialState::Asleep => {
+                let services = Service::get_all().await?;
+                let mut groups = Vec::new();
+                for group in State::get_all_groups(&services).await?.into_keys() {
+                    if restored.contains(&group) {
+                        continue;
                     }
+                    info!("Initial state of group '{group}' > Asleep");
+                    State::set_group(&group, StateKind::Asleep).await?;
+                    let state = GroupState::new(StateKind::Asleep, NotificationKind::NoActivity);
+                    groups.push((group, state));
                 }
-            } else {
-                // Service is not already in the state, so it's a new one, by default its
-                // considered as 'activity' to prevent instante sleeping when resources are created
-                debug!("Service '{service_id}' is new > Activity ");
-                return Ok(Notification::new(NotificationKind::Activity));
+                groups
+            }
+            InitialState::Detect => State::detect_groups()
+                .await?
+                .into_iter()
+                .filter(|(group, _)| !restored.contains(group))
+                .collect(),
+        };
+        if groups.is_empty() {
+            return Ok(());
+        }
+
+        let snapshot = {
+            let mut state = STATE
+                .lock()
+                .map_err(|e| StateError::LockError(format!("{e:?}")))?;
+            state.groups.extend(groups);
+            Snapshot::of(&state)
+        };
+        State::save(&snapshot).await;
+        Ok(())
+    }
+
+    /// Restore the persisted state, returning the names of the restored groups
+    async fn restore() -> Result<BTreeSet<String>, StateError> {
+        let snapshot = match persistence::load().await {
+            Ok(Some(snapshot)) => snapshot,
+            Ok(None) => return Ok(BTreeSet::new()),
+            Err(e) => {
+                warn!("Can't read the persisted state, starting from scratch : {e}");
+                return Ok(BTreeSet::new());
             }
+        };
+
+        let (groups, metrics) = snapshot.restore();
+        let restored = groups.keys().cloned().collect();
+        for (group, state) in &groups {
+            info!(
+                "State of group '{group}' restored > {} since {:?} ({:?} ago)",
+                state.kind,
+                state.since.kind,
+                state.since.timestamp.elapsed()
+            );
         }
-        // Finally, if no new service, no new ingress pod and no new connections
-        debug!("No new service, no new ingress, no new connections > No Activity");
-        Ok(Notification::new(NotificationKind::NoActivity))
+
+        let mut state = STATE
+            .lock()
+            .map_err(|e| StateError::LockError(format!("{e:?}")))?;
+        state.groups = groups;
+        state.metrics = metrics;
+        Ok(restored)
     }
 
-    pub async fn update_from_notification(notification: Notification) -> Result<(), StateError> {
-        let mut action: Option<StateKind> = None;
+    /// Persist `snapshot`, failures are only logged so they don't stop the controller
+    async fn save(snapshot: &Snapshot) {
+        if let Err(e) = persistence::save(snapshot).await {
+            warn!("Can't persist the state : {e}");
+        }
+    }
 
-        {
+    /// Detect the state of each group from its Deployments, StatefulSets, Scalables and Services.
+    ///
+    /// A group is asleep if all of its resources are asleep, awake if none are. A partially asleep
+    /// group is awake without activity, so it is set asleep after the sleepiness duration.
+    async fn detect_groups() -> Result<Vec<(String, GroupState)>, StateError> {
+        let group_by = group_by();
+        let mut asleep: HashMap<String, Vec<bool>> = HashMap::new();
+        for deploy in Deploy::get_all().await? {
+            let group = asleep.entry(deploy.group(&group_by)).or_default();
+            group.push(deploy.is_asleep());
+        }
+        for statefulset in StatefulSet::get_all().await? {
+            let group = asleep.entry(statefulset.group(&group_by)).or_default();
+            group.push(statefulset.is_asleep());
+        }
+        for scalable in Scalable::get_all().await? {
+            let group = asleep.entry(scalable.group(&group_by)).or_default();
+            group.push(scalable.is_asleep());
+        }
+        for service in Service::get_all().await? {
+            let group = asleep.entry(service.group(&group_by)).or_default();
+            group.push(service.is_asleep());
+        }
+
+        let groups = asleep
+            .into_iter()
+            .map(|(group, asleep)| {
+                let state = if asleep.iter().all(|a| *a) {
+                    info!("Initial state of group '{group}' detected > Asleep");
+                    GroupState::new(StateKind::Asleep, NotificationKind::NoActivity)
+                } else if asleep.iter().any(|a| *a) {
+                    warn!(
+                        "Group '{group}' is partially asleep : it will be set asleep unless it receives activity"
+                    );
+                    GroupState::new(StateKind::Awake, NotificationKind::NoActivity)
+                } else {
+                    info!("Initial state of group '{group}' detected > Awake");
+                    GroupState::default()
+                };
+                (group, state)
+            })
+            .collect();
+        Ok(groups)
+    }
+
+    /// Update the metrics from a new scrape, returning the notification of each of `groups`
+    pub fn apply_metrics(
+        &mut self,
+        new_metrics: &HashMap<String, HashMap<String, u64>>,
+        resolver: &TraefikServiceResolver,
+        services: &[Service],
+        groups: BTreeMap<String, Sleepiness>,
+    ) -> HashMap<String, Notification> {
+        self.metrics.update(new_metrics);
+        let notifications =
+            State::create_notifications_from_metrics(&self.metrics, resolver, services, &groups);
+        self.sleepiness = groups;
+        notifications
+    }
+
+    fn create_notifications_from_metrics(
+        metrics: &Metrics,
+        resolver: &TraefikServiceResolver,
+        services: &[Service],
+        groups: &BTreeMap<String, Sleepiness>,
+    ) -> HashMap<String, Notification> {
+        let group_by = group_by();
+        let mut active_groups = BTreeSet::new();
+
+        for service_id in metrics.service_ids() {
+            let target_ids = resolver.resolve(service_id);
+            let service_groups: BTreeSet<String> = services
+                .iter()
+                .filter(|s| target_ids.contains(&s.id))
+                .map(|s| s.group(&group_by))
+                .collect();
+
+            if service_groups.is_empty() {
+                debug!("Service '{service_id}' doesn't target any managed Service, skipping");
+                continue;
+            }
+
+            // New services and reset counters are counted as new requests, so resources are not
+            // set asleep right after being created. Counters of new ingress pods are a baseline
+            let nb_new_connection = metrics.delta(service_id);
+            if nb_new_connection > 0 {
+                debug!(
+                    "Service '{service_id}' has proceed {nb_new_connection} new connection > Activity for groups {service_groups:?}"
+                );
+                active_groups.extend(service_groups);
+            }
+        }
+
+        // Finally, groups with no new connections.
+        // Groups without Service can't receive traffic, they are left to schedules and holidays
+        let groups_with_service: BTreeSet<String> =
+            services.iter().map(|s| s.group(&group_by)).collect();
+        groups
+            .keys()
+            .filter_map(|group| {
+                let kind = if active_groups.contains(group) {
+                    NotificationKind::Activity
+                } else if groups_with_service.contains(group) {
+                    debug!("No new connections > No Activity for group '{group}'");
+                    NotificationKind::NoActivity
+                } else {
+                    debug!("Group '{group}' has no Service, skipping");
+                    return None;
+                };
+                Some((group.clone(), Notification::new(kind)))
+            })
+            .collect()
+    }
+
+    #[instrument(level = "info", skip(notification))]
+    pub async fn update_from_notification(
+        group: &str,
+        notification: Notification,
+    ) -> Result<(), StateError> {
+        let (action, snapshot) = {
             // explaination of the error if remove this scoped block
             debug!("Update state from Notification");
-            let mut state = STATE
+            let mut states = STATE
                 .lock()
                 .map_err(|e| StateError::LockError(format!("{e:?}")))?;
+            let update = states.notify(group, notification);
+            let changed = update.transition || update.suppressed;
+            (update.action, changed.then(|| Snapshot::of(&states)))
+        };
 
-            match (&state.since.kind, &notification.kind) {
-                (NotificationKind::Activity, NotificationKind::Activity) => {
-                    info!("State do not change > {:?}", &state.since.kind);
-                }
-                (NotificationKind::Activity, NotificationKind::NoActivity) => {
-                    info!("State change > {:?}", &state.since.kind);
-                    state.since = notification; // new state kind since this new notification
-                }
-                (NotificationKind::NoActivity, NotificationKind::NoActivity) => {
-                    let sleepiness_duration = notification.timestamp - state.since.timestamp;
-                    let max_sleepiness_duration = match SLEEPINESS_DURATION.get() {
-                        Some(s) => *s,
-                        None => panic!("SLEEPINESS_DURATION should be set a this step"),
-                    };
-                    if sleepiness_duration >= max_sleepiness_duration
-                        && state.kind != StateKind::Asleep
-                    {
-                        // The application has been in sleepiness mode for too long; it must set asleep.
-                        debug!(
-                            "Sleepiness duration exceeded: maximum sleepiness duration is {max_sleepiness_duration:?}s, but the state was in this condition {sleepiness_duration:?}s."
-                        );
-                        info!("State change > Asleep");
-                        state.kind = StateKind::Asleep;
-                        action = Some(StateKind::Asleep);
-                    }
+        if let Some(snapshot) = snapshot {
+            State::save(&snapshot).await;
+        }
+
+        match action {
+            Some(kind) => State::set_group(group, kind).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Update the state of `group` from `notification`, without acting on its resources
+    pub fn notify(&mut self, group: &str, notification: Notification) -> Update {
+        let mut update = Update::default();
+        let max_sleepiness = self
+            .sleepiness
+            .get(group)
+            .cloned()
+            .unwrap_or_else(|| Sleepiness::config(sleepiness_duration()));
+        let state = self.groups.entry(group.to_string()).or_default();
+        let suppressing = state.suppressing.take();
+        let now = notification.timestamp;
+
+        match (&state.since.kind, &notification.kind) {
+            (NotificationKind::Activity, NotificationKind::Activity) => {
+                info!("State do not change > {:?}", &state.since.kind);
+            }
+            (NotificationKind::Activity, NotificationKind::NoActivity) => {
+                info!("State change > {:?}", &state.since.kind);
+                state.since = notification; // new state kind since this new notification
+                update.transition = true;
+            }
+            (NotificationKind::NoActivity, NotificationKind::NoActivity) => {
+                let sleepiness_duration = notification.timestamp - state.since.timestamp;
+                let exceeded = sleepiness_duration >= max_sleepiness.duration
+                    && state.kind != StateKind::Asleep;
+                if exceeded && schedule::hold() == Some(StateKind::Awake) {
+                    debug!("Sleepiness duration exceeded, but held awake by schedule");
+                } else if exceeded && state.fall_asleep(now) {
+                    // The application has been in sleepiness mode for too long; it must set asleep.
+                    debug!(
+                        "Sleepiness duration exceeded: maximum sleepiness duration is {max_sleepiness}, but the state was in this condition {sleepiness_duration:?}."
+                    );
+                    info!("State change > Asleep");
+                    update.action = Some(StateKind::Asleep);
+                    update.transition = true;
+                } else {
                     info!("State do not change > {:?}", &state.since.kind);
                 }
-                (NotificationKind::NoActivity, NotificationKind::Activity) => {
-                    // The application has received a connection but is asleep, must be waked up.
-                    state.since = notification;
-                    state.kind = StateKind::Awake;
-                    info!("State change to Awake ");
-                    action = Some(StateKind::Awake);
-                }
-            };
+            }
+            (NotificationKind::NoActivity, NotificationKind::Activity)
+                if state.kind == StateKind::Asleep
+                    && schedule::hold() == Some(StateKind::Asleep) =>
+            {
+                info!("Activity ignored > held asleep by schedule");
+            }
+            (NotificationKind::NoActivity, NotificationKind::Activity) => {
+                // The application has received a connection but is asleep, must be waked up.
+                state.wake(now);
+                state.since = notification;
+                info!("State change to Awake ");
+                update.action = Some(StateKind::Awake);
+                update.transition = true;
+            }
+        };
+
+        // a suppressed transition is counted once, until it happens or isn't wanted anymore
+        if let Some(kind) = state.suppressing
+            && state.suppressing != suppressing
+        {
+            state.suppressed += 1;
+            info!(
+                "Transition to {kind} suppressed > {} transition(s) suppressed so far",
+                state.suppressed
+            );
+            update.suppressed = true;
         }
+        update
+    }
 
-        match action {
-            Some(StateKind::Asleep) => {
-                debug!("Making all Deploy 'Asleep'");
-                for deploy in Deploy::get_all().await?.iter_mut() {
+    /// Set all groups to the state of `schedule`, whatever their activity.
+    ///
+    /// Schedules waking groups up are skipped during holidays.
+    pub async fn apply_schedule(schedule: &Schedule) -> Result<(), StateError> {
+        if schedule.state == StateKind::Awake
+            && let Some(holiday) = calendar::calendar().current(Utc::now())
+        {
+            info!("Schedule {schedule} skipped > holiday {holiday}");
+            // traffic still wakes groups during holidays
+            schedule::clear_hold();
+            return Ok(());
+        }
+
+        info!("Schedule {schedule} > all groups {}", schedule.state);
+        schedule::set_hold(schedule);
+        State::set_all(schedule.state).await
+    }
+
+    /// Set all groups asleep when a holiday of the calendar starts
+    pub async fn apply_holidays() -> Result<(), StateError> {
+        let Some(holiday) = calendar::entered(Utc::now()) else {
+            return Ok(());
+        };
+        info!("Holiday {holiday} > all groups asleep");
+        // traffic still wakes groups during holidays
+        schedule::clear_hold();
+        State::set_all(StateKind::Asleep).await
+    }
+
+    /// Set all groups to `kind`, whatever their activity
+    async fn set_all(kind: StateKind) -> Result<(), StateError> {
+        // awake groups start a new sleepiness countdown
+        let since = match kind {
+            StateKind::Awake => NotificationKind::Activity,
+            StateKind::Asleep => NotificationKind::NoActivity,
+        };
+        let services = Service::get_all().await?;
+        for group in State::get_all_groups(&services).await?.into_keys() {
+            State::set_group(&group, kind).await?;
+            let mut state = STATE
+                .lock()
+                .map_err(|e| StateError::LockError(format!("{e:?}")))?;
+            let group_state = state.groups.entry(group).or_default();
+            group_state.kind = kind;
+            group_state.since = Notification::new(since);
+            group_state.last_transition = Some(clock::now());
+            group_state.suppressing = None;
+        }
+
+        let snapshot = {
+            let state = STATE
+                .lock()
+                .map_err(|e| StateError::LockError(format!("{e:?}")))?;
+            Snapshot::of(&state)
+        };
+        State::save(&snapshot).await;
+        Ok(())
+    }
+
+    /// Apply the last schedule which ran before kubesleeper started, if it holds its state
+    pub async fn resume_schedule() -> Result<(), StateError> {
+        let schedules = schedule::schedules();
+        match schedule::current(&schedules, schedule::timezone(), Utc::now()) {
+            Some(current) if current.hold => {
+                info!("Resuming schedule {current}");
+                State::apply_schedule(current).await
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Set all resources of a group to the desired state
+    async fn set_group(group: &str, kind: StateKind) -> Result<(), StateError> {
+        let group_by = group_by();
+        debug!("Making all resources of group '{group}' '{kind}'");
+
+        let mut deploys = Deploy::get_all().await?;
+        let mut statefulsets = StatefulSet::get_all().await?;
+        let mut scalables = Scalable::get_all().await?;
+        let mut cronjobs = CronJob::get_all().await?;
+        let mut services = Service::get_all().await?;
+
+        // Autoscalers of the group workloads, so they don't fight their scaling
+        let targets: Vec<_> = deploys
+            .iter()
+            .filter(|d| d.group(&group_by) == group)
+            .map(Deploy::scale_target)
+            .chain(
+                statefulsets
+                    .iter()
+                    .filter(|s| s.group(&group_by) == group)
+                    .map(StatefulSet::scale_target),
+            )
+            .chain(
+                scalables
+                    .iter()
+                    .filter(|s| s.group(&group_by) == group)
+                    .map(Scalable::scale_target),
+            )
+            .collect();
+        let mut hpas = Hpa::get_all_scaling(&targets).await?;
+        let mut scaled_objects = ScaledObject::get_all_scaling(&targets).await?;
+
+        let deploys = deploys.iter_mut().filter(|d| d.group(&group_by) == group);
+        let statefulsets = statefulsets
+            .iter_mut()
+            .filter(|s| s.group(&group_by) == group);
+        let scalables = scalables.iter_mut().filter(|s| s.group(&group_by) == group);
+        let cronjobs = cronjobs.iter_mut().filter(|c| c.group(&group_by) == group);
+        let services = services.iter_mut().filter(|s| s.group(&group_by) == group);
+
+        match kind {
+            StateKind::Asleep => {
+                // CronJobs are suspended first so they don't wake the group back up
+                for cronjob in cronjobs {
+                    cronjob.sleep().await?
+                }
+                // HPAs are disabled before their workloads are scaled down
+                for hpa in hpas.iter_mut() {
+                    hpa.sleep().await?
+                }
+                for deploy in deploys {
                     deploy.sleep().await?
                 }
-                for service in Service::get_all().await?.iter_mut() {
+                for statefulset in statefulsets {
+                    statefulset.sleep().await?
+                }
+                for scalable in scalables {
+                    scalable.sleep().await?
+                }
+                // ScaledObjects are paused once their workloads replicas are stored
+                for scaled_object in scaled_objects.iter_mut() {
+                    scaled_object.sleep().await?
+                }
+                for service in services {
                     service.sleep().await?
                 }
             }
-            Some(StateKind::Awake) => {
-                debug!("Making all Deploy 'Awake'");
-                for deploy in Deploy::get_all().await?.iter_mut() {
+            StateKind::Awake => {
+                // a paused ScaledObject keeps its workload at 0 replicas
+                for scaled_object in scaled_objects.iter_mut() {
+                    scaled_object.wake().await?
+                }
+                for deploy in deploys {
                     deploy.wake().await?
                 }
-                for service in Service::get_all().await?.iter_mut() {
+                for statefulset in statefulsets {
+                    statefulset.wake().await?
+                }
+                for scalable in scalables {
+                    scalable.wake().await?
+                }
+                // HPAs are restored once their workloads are up
+                for hpa in hpas.iter_mut() {
+                    hpa.wake().await?
+                }
+                for service in services {
                     service.wake().await?
                 }
+                for cronjob in cronjobs {
+                    cronjob.wake().await?
+                }
             }
-            None => {}
         };
         Ok(())
     }
 
+    /// Names of all groups of the managed resources, with their sleepiness duration
+    pub async fn get_all_groups(
+        services: &[Service],
+    ) -> Result<BTreeMap<String, Sleepiness>, StateError> {
+        let group_by = group_by();
+
+        let mut members = State::service_members(services);
+        members.extend(Deploy::get_all().await?.iter().map(|d| {
+            let resource = format!("Deployment {}", d.id);
+            (d.group(&group_by), d.sleepiness_duration(), resource)
+        }));
+        members.extend(StatefulSet::get_all().await?.iter().map(|s| {
+            let resource = format!("StatefulSet {}", s.id);
+            (s.group(&group_by), s.sleepiness_duration(), resource)
+        }));
+        members.extend(Scalable::get_all().await?.iter().map(|s| {
+            let resource = format!("{} {}", s.kind, s.id);
+            (s.group(&group_by), s.sleepiness_duration(), resource)
+        }));
+        members.extend(CronJob::get_all().await?.iter().map(|c| {
+            let resource = format!("CronJob {}", c.id);
+            (c.group(&group_by), c.sleepiness_duration(), resource)
+        }));
+        Ok(State::groups_of(members))
+    }
+
+    /// (group, sleepiness duration annotation, resource) of each Service
+    pub fn service_members(services: &[Service]) -> Vec<(String, Option<Duration>, String)> {
+        let group_by = group_by();
+        services
+            .iter()
+            .map(|s| {
+                (
+                    s.group(&group_by),
+                    s.sleepiness_duration(),
+                    format!("Service {}", s.id),
+                )
+            })
+            .collect()
+    }
+
+    /// Groups of `members`, with their sleepiness duration
+    pub fn groups_of(
+        members: Vec<(String, Option<Duration>, String)>,
+    ) -> BTreeMap<String, Sleepiness> {
+        let mut groups: BTreeMap<String, Sleepiness> = BTreeMap::new();
+        for (group, duration, resource) in members {
+            let sleepiness = match duration {
+                Some(duration) => Sleepiness::annotation(duration, resource),
+                None => Sleepiness::config(sleepiness_duration()),
+            };
+            let sleepiness = match groups.remove(&group) {
+                Some(other) => other.merge(sleepiness),
+                None => sleepiness,
+            };
+            groups.insert(group, sleepiness);
+        }
+        groups
+    }
+
+    /// Update state from a request received by the kubesleeper server for `host`.
+    ///
+    /// Only the groups of the Services routed for `host` by Ingresses and IngressRoutes are
+    /// woken up, as resolved at the last refresh. If none can be found, all groups are notified.
+    pub async fn update_from_host_activity(host: Option<&str>) -> Result<(), StateError> {
+        let group_by = group_by();
+        let services = Service::get_all().await?;
+
+        let resolver = match RESOLVER.get() {
+            Some(resolver) => resolver,
+            None => Arc::new(TraefikServiceResolver::new(&services).await?),
+        };
+        let service_ids = host
+            .map(|host| resolver.resolve_host(host))
+            .unwrap_or_default();
+
+        let mut groups: BTreeSet<String> = services
+            .iter()
+            .filter(|s| service_ids.contains(&s.id))
+            .map(|s| s.group(&group_by))
+            .collect();
+
+        if groups.is_empty() {
+            debug!("No managed Service found for host {host:?} : notifying all groups");
+            groups = State::get_all_groups(&services)
+                .await?
+                .into_keys()
+                .collect();
+        }
+
+        for group in groups {
+            State::update_from_notification(&group, Notification::new(NotificationKind::Activity))
+                .await?;
+        }
+        Ok(())
+    }
+
     pub async fn update_from_metrics(
         new_metrics: HashMap<String, HashMap<String, u64>>,
     ) -> Result<(), StateError> {
         debug!("Updating state from metrics");
-        // Update notification
-        State::update_from_notification(State::create_notification_from_metrics(&new_metrics)?)
-            .await?;
+        let services = Service::get_all().await?;
+        let groups = State::get_all_groups(&services).await?;
+        let resolver = Arc::new(TraefikServiceResolver::new(&services).await?);
+        RESOLVER.set(resolver.clone());
 
-        // Update metrics
-        STATE
-            .lock()
-            .map_err(|e| StateError::LockError(format!("{e:?}")))?
-            .metrics = new_metrics;
+        let notifications = {
+            let mut state = STATE
+                .lock()
+                .map_err(|e| StateError::LockError(format!("{e:?}")))?;
+            state.apply_metrics(&new_metrics, &resolver, &services, groups)
+        };
+
+        // Update notification of each group
+        for (group, notification) in notifications {
+            State::update_from_notification(&group, notification).await?;
+        }
         Ok(())
     }
 }
@@ -179,6 +752,16 @@ impl State {
     fields(uuid = %uuid)
 )]
 async fn process(uuid: Uuid) {
+    if redirection::redirection() == Redirection::EndpointSlice
+        && let Err(e) = redirection::sync_endpoint_slices().await
+    {
+        error!("Failed to sync EndpointSlices : {e}");
+    }
+
+    if let Err(e) = State::apply_holidays().await {
+        error!("Failed to apply holidays : {e}");
+    }
+
     let metrics = Traefik::get_metrics().await;
 
     State::update_from_metrics(metrics.map_err(|e| e.to_string()).unwrap())
@@ -187,38 +770,306 @@ async fn process(uuid: Uuid) {
         .unwrap();
 }
 
-pub async fn create_schedule(refresh_interval: NonZeroU32) -> JobScheduler {
-    let sched = JobScheduler::new().await.unwrap();
+/// Resolver of the last refresh, reused for the requests received by the server
+static RESOLVER: Swappable<Arc<TraefikServiceResolver>> = Swappable::new();
 
-    sched
-        .add(
-            Job::new_async(format!("1/{refresh_interval} * * * * *"), |uuid, mut l| {
-                Box::pin(async move {
-                    {
-                        process(Uuid::new_v4()).await
-                    }
+/// Scheduler of the refresh job, only created on the leader
+static SCHEDULER: Swappable<JobScheduler> = Swappable::new();
 
-                    // Query the next execution time for this job
-                    let _next_tick = l.next_tick_for_job(uuid).await;
-                })
-            })
-            .unwrap(),
-        )
+/// Id of the job refreshing the state, replaced when the refresh interval changes
+static REFRESH_JOB: Swappable<Uuid> = Swappable::new();
+
+/// Ids of the jobs of the schedules, replaced when the schedules change
+static SCHEDULE_JOBS: Swappable<Vec<Uuid>> = Swappable::new();
+
+fn create_refresh_job(refresh_interval: Duration) -> Result<Job, JobSchedulerError> {
+    Job::new_repeated_async(refresh_interval, |uuid, mut l| {
+        Box::pin(async move {
+            {
+                process(Uuid::new_v4()).await
+            }
+
+            // Query the next execution time for this job
+            let _next_tick = l.next_tick_for_job(uuid).await;
+        })
+    })
+}
+
+pub async fn create_schedule() -> JobScheduler {
+    let refresh_interval = match REFRESH_INTERVAL.get() {
+        Some(r) => r,
+        None => panic!("REFRESH_INTERVAL should be set a this step"),
+    };
+    let sched = JobScheduler::new().await.unwrap();
+
+    let job_id = sched
+        .add(create_refresh_job(refresh_interval).unwrap())
+        .await
+        .unwrap();
+    REFRESH_JOB.set(job_id);
+    SCHEDULER.set(sched.clone());
+    replace_schedules(&schedule::schedules(), schedule::timezone())
         .await
         .unwrap();
     info!("Running scheduler");
     sched
 }
-impl Default for State {
+
+fn create_schedule_job(schedule: Schedule, timezone: Timezone) -> Result<Job, JobSchedulerError> {
+    let cron = schedule.cron.as_str().to_string();
+    Job::new_async_tz(cron, timezone.0, move |_uuid, _l| {
+        let schedule = schedule.clone();
+        Box::pin(async move {
+            if let Err(e) = State::apply_schedule(&schedule).await {
+                error!("Failed to apply schedule {schedule} : {e}");
+            }
+        })
+    })
+}
+
+/// Replace the jobs of the schedules by new ones, running `schedules` in `timezone`.
+///
+/// Does nothing if the scheduler isn't running (when not being the leader).
+pub async fn replace_schedules(
+    schedules: &[Schedule],
+    timezone: Timezone,
+) -> Result<(), JobSchedulerError> {
+    let Some(sched) = SCHEDULER.get() else {
+        return Ok(());
+    };
+    for job_id in SCHEDULE_JOBS.get().unwrap_or_default() {
+        sched.remove(&job_id).await?;
+    }
+
+    let mut job_ids = Vec::new();
+    for schedule in schedules {
+        info!("Adding schedule {schedule} in timezone {timezone}");
+        job_ids.push(
+            sched
+                .add(create_schedule_job(schedule.clone(), timezone)?)
+                .await?,
+        );
+    }
+    SCHEDULE_JOBS.set(job_ids);
+    Ok(())
+}
+
+/// Replace the refresh job by a new one, running every `refresh_interval`.
+///
+/// Does nothing if the scheduler isn't running (when not being the leader).
+pub async fn reschedule(refresh_interval: Duration) -> Result<(), JobSchedulerError> {
+    let Some(sched) = SCHEDULER.get() else {
+        return Ok(());
+    };
+    if let Some(job_id) = REFRESH_JOB.get() {
+        sched.remove(&job_id).await?;
+    }
+    let job_id = sched.add(create_refresh_job(refresh_interval)?).await?;
+    REFRESH_JOB.set(job_id);
+    info!(
+        "Refresh interval set to {}",
+        config::duration::format(&refresh_interval)
+    );
+    Ok(())
+}
+impl Default for GroupState {
+    /// State of a new group, created awake as if it just received activity
     fn default() -> Self {
-        // TODO: chose first awake or asleep from config
-        State {
-            since: Notification {
-                kind: NotificationKind::Activity,
-                timestamp: Instant::now(),
-            },
-            kind: StateKind::Awake,
-            metrics: Default::default(),
+        GroupState::new(StateKind::Awake, NotificationKind::Activity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GROUP: &str = "group";
+
+    /// The durations and the schedule hold are global : tests setting them don't run together
+    static GLOBALS: Mutex<()> = Mutex::new(());
+
+    struct Test {
+        state: State,
+        start: Instant,
+        _globals: std::sync::MutexGuard<'static, ()>,
+    }
+
+    impl Test {
+        /// A group in `kind`, set asleep after 10s without activity
+        fn new(kind: StateKind, min_awake_duration: u64, cooldown: u64) -> Self {
+            let globals = GLOBALS
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner);
+            MIN_AWAKE_DURATION.set(Duration::from_secs(min_awake_duration));
+            COOLDOWN.set(Duration::from_secs(cooldown));
+            schedule::clear_hold();
+
+            let start = Instant::now();
+            let mut state = State::default();
+            state.sleepiness.insert(
+                GROUP.to_string(),
+                Sleepiness::config(Duration::from_secs(10)),
+            );
+            let since = match kind {
+                StateKind::Awake => NotificationKind::Activity,
+                StateKind::Asleep => NotificationKind::NoActivity,
+            };
+            let mut group = GroupState::new(kind, since);
+            group.since.timestamp = start;
+            state.groups.insert(GROUP.to_string(), group);
+            Test {
+                state,
+                start,
+                _globals: globals,
+            }
+        }
+
+        /// Notify the group of `kind` at `seconds` after the start
+        fn notify(&mut self, kind: NotificationKind, seconds: u64) -> Update {
+            let notification = Notification {
+                kind,
+                timestamp: self.start + Duration::from_secs(seconds),
+            };
+            self.state.notify(GROUP, notification)
+        }
+
+        fn group(&self) -> &GroupState {
+            &self.state.groups[GROUP]
         }
     }
+
+    fn hold(state: StateKind) {
+        schedule::set_hold(&Schedule {
+            cron: "0 0 8 * * *".parse().unwrap(),
+            state,
+            hold: true,
+        });
+    }
+
+    #[test]
+    fn sleepiness_threshold() {
+        let mut test = Test::new(StateKind::Awake, 0, 0);
+        let update = test.notify(NotificationKind::NoActivity, 0);
+        assert!(update.transition);
+        assert_eq!(update.action, None);
+
+        assert_eq!(test.notify(NotificationKind::NoActivity, 9).action, None);
+        assert_eq!(
+            test.notify(NotificationKind::NoActivity, 10).action,
+            Some(StateKind::Asleep)
+        );
+        assert_eq!(test.group().kind, StateKind::Asleep);
+
+        // activity restarts the countdown
+        assert_eq!(
+            test.notify(NotificationKind::Activity, 11).action,
+            Some(StateKind::Awake)
+        );
+        test.notify(NotificationKind::NoActivity, 12);
+        assert_eq!(test.notify(NotificationKind::NoActivity, 21).action, None);
+        assert_eq!(
+            test.notify(NotificationKind::NoActivity, 22).action,
+            Some(StateKind::Asleep)
+        );
+        assert_eq!(test.group().suppressed, 0);
+    }
+
+    #[test]
+    fn min_awake_duration_blocks_early_sleep() {
+        let mut test = Test::new(StateKind::Asleep, 60, 0);
+        assert_eq!(
+            test.notify(NotificationKind::Activity, 0).action,
+            Some(StateKind::Awake)
+        );
+        test.notify(NotificationKind::NoActivity, 1);
+
+        // sleepiness duration exceeded, but awake for less than the minimum
+        let update = test.notify(NotificationKind::NoActivity, 20);
+        assert_eq!(update.action, None);
+        assert!(update.suppressed);
+        assert_eq!(test.group().kind, StateKind::Awake);
+
+        assert_eq!(
+            test.notify(NotificationKind::NoActivity, 60).action,
+            Some(StateKind::Asleep)
+        );
+    }
+
+    #[test]
+    fn cooldown_only_delays_sleep() {
+        let mut test = Test::new(StateKind::Awake, 0, 60);
+        test.notify(NotificationKind::NoActivity, 0);
+        assert_eq!(
+            test.notify(NotificationKind::NoActivity, 10).action,
+            Some(StateKind::Asleep)
+        );
+
+        // a wake is never suppressed, even during the cooldown
+        assert_eq!(
+            test.notify(NotificationKind::Activity, 11).action,
+            Some(StateKind::Awake)
+        );
+        assert_eq!(test.group().kind, StateKind::Awake);
+
+        test.notify(NotificationKind::NoActivity, 12);
+        assert_eq!(test.notify(NotificationKind::NoActivity, 30).action, None);
+        assert_eq!(test.group().kind, StateKind::Awake);
+        assert_eq!(
+            test.notify(NotificationKind::NoActivity, 71).action,
+            Some(StateKind::Asleep)
+        );
+        assert_eq!(test.group().suppressed, 1);
+    }
+
+    #[test]
+    fn suppressed_transition_counted_once() {
+        let mut test = Test::new(StateKind::Asleep, 60, 0);
+        test.notify(NotificationKind::Activity, 0);
+        test.notify(NotificationKind::NoActivity, 1);
+
+        assert!(test.notify(NotificationKind::NoActivity, 20).suppressed);
+        assert!(!test.notify(NotificationKind::NoActivity, 30).suppressed);
+        assert!(!test.notify(NotificationKind::NoActivity, 40).suppressed);
+        assert_eq!(test.group().suppressed, 1);
+
+        // activity cancels the suppressed sleep : the next one is counted again
+        test.notify(NotificationKind::Activity, 41);
+        test.notify(NotificationKind::NoActivity, 42);
+        assert!(test.notify(NotificationKind::NoActivity, 52).suppressed);
+        assert_eq!(test.group().suppressed, 2);
+    }
+
+    #[test]
+    fn schedule_holds_asleep() {
+        let mut test = Test::new(StateKind::Asleep, 0, 0);
+        hold(StateKind::Asleep);
+
+        let update = test.notify(NotificationKind::Activity, 0);
+        assert_eq!(update.action, None);
+        assert!(!update.transition);
+        assert_eq!(test.group().kind, StateKind::Asleep);
+
+        schedule::clear_hold();
+        assert_eq!(
+            test.notify(NotificationKind::Activity, 1).action,
+            Some(StateKind::Awake)
+        );
+    }
+
+    #[test]
+    fn schedule_holds_awake() {
+        let mut test = Test::new(StateKind::Awake, 0, 0);
+        hold(StateKind::Awake);
+
+        test.notify(NotificationKind::NoActivity, 0);
+        assert_eq!(test.notify(NotificationKind::NoActivity, 3600).action, None);
+        assert_eq!(test.group().kind, StateKind::Awake);
+        assert_eq!(test.group().suppressed, 0);
+
+        schedule::clear_hold();
+        assert_eq!(
+            test.notify(NotificationKind::NoActivity, 3601).action,
+            Some(StateKind::Asleep)
+        );
+    }
 }
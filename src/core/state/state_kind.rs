@@ -1,12 +1,13 @@
 use core::fmt;
 
 use clap::ValueEnum;
-use serde::Serialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 
 use super::StateError;
 
 // ValueEnum for allowing Clap to take StateKind as argument type
-#[derive(Eq, PartialEq, Debug, Serialize, Clone, ValueEnum, Copy)]
+#[derive(Eq, PartialEq, Debug, Serialize, Deserialize, Clone, ValueEnum, Copy, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum StateKind {
     Asleep,
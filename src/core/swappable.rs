@@ -0,0 +1,23 @@
+use std::sync::{PoisonError, RwLock};
+
+/// A global value which can be replaced at runtime : a configuration value replaced when the
+/// configuration is reloaded, or a runtime state (like the running scheduler)
+pub struct Swappable<T>(RwLock<Option<T>>);
+
+impl<T: Clone> Swappable<T> {
+    pub const fn new() -> Self {
+        Swappable(RwLock::new(None))
+    }
+
+    pub fn set(&self, value: T) {
+        *self.0.write().unwrap_or_else(PoisonError::into_inner) = Some(value);
+    }
+
+    /// Current value, `None` if it has never been set
+    pub fn get(&self) -> Option<T> {
+        self.0
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
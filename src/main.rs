@@ -7,20 +7,29 @@ use std::process;
 use clap::{Parser, Subcommand, ValueEnum};
 use tokio_cron_scheduler::JobSchedulerError;
 
-use crate::core::config;
-use crate::core::resource::deploy::Deploy;
-use crate::core::state::state::SLEEPINESS_DURATION;
+use crate::core::config::{
+    self, Config,
+    layers::{Layer, Override},
+    reload, validate,
+};
+use crate::core::resource::{deploy::Deploy, dry_run};
 use crate::core::state::state_kind::StateKind;
 use crate::core::{
     ingress::error::IngressError,
+    leader,
     logger::{self, init_logger},
     resource, server,
     server::error::ServerError,
-    state::state::create_schedule,
+    state::{
+        StateError,
+        initial_state::InitialState,
+        state::{State, create_schedule},
+    },
 };
 
 mod msg;
 use crate::msg::{Message, error};
+mod simulate;
 mod status;
 use crate::status::status;
 
@@ -38,7 +47,7 @@ struct Cli {
     /// Human readable mode for logging
     readable_log: bool,
 
-    #[arg(long)]
+    #[arg(long, global = true)]
     /// Path to the kubesleeper YAML configuration file
     config: Option<PathBuf>,
 }
@@ -46,14 +55,71 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     /// start kubesleeper service
-    Start,
+    Start {
+        /// Log the patches of the resources instead of applying them
+        #[arg(long)]
+        dry_run: bool,
+    },
 
     /// Describe k8s status with
-    Status,
+    Status {
+        /// Label selector of the resources to describe, overriding the configured one
+        #[arg(long)]
+        selector: Option<String>,
+    },
 
     #[command(subcommand)]
     /// Execute specific action
     Msg(Message),
+
+    #[command(subcommand)]
+    /// Inspect configuration files
+    Config(ConfigCommand),
+
+    /// Replay recorded metrics offline, printing when groups would have been set asleep or awake
+    ///
+    /// Only the recorded traffic is replayed : the schedules and holidays of the configuration
+    /// are ignored
+    Simulate {
+        /// Directory of the Prometheus dumps, named by their time, and of the Services (services.yaml)
+        #[arg(long)]
+        metrics: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Print the JSON Schema of the configuration file, for editor completion
+    Schema,
+
+    /// Check a configuration file, listing all of its errors
+    Validate {
+        /// Path to the kubesleeper YAML configuration file to check
+        file: PathBuf,
+    },
+}
+
+impl Commands {
+    /// Label selector set on the command line, overriding the configured one
+    fn selector(&self) -> Option<&str> {
+        match self {
+            Commands::Status { selector } | Commands::Msg(Message::Set { selector, .. }) => {
+                selector.as_deref()
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether resources must only be logged, not patched
+    fn dry_run(&self) -> bool {
+        match self {
+            Commands::Start { dry_run }
+            | Commands::Msg(Message::Set { dry_run, .. } | Message::SetRsc { dry_run, .. }) => {
+                *dry_run
+            }
+            _ => false,
+        }
+    }
 }
 
 #[derive(Debug, Clone, ValueEnum)]
@@ -111,6 +177,49 @@ enum Error {
 
     #[error(transparent)]
     ConfigError(#[from] config::ConfigError),
+
+    #[error(transparent)]
+    StateError(#[from] StateError),
+
+    #[error(transparent)]
+    Simulate(#[from] simulate::error::Simulate),
+}
+
+/// Run a `config` subcommand, which doesn't need the running configuration
+fn config_command(command: ConfigCommand) -> Result<(), config::ConfigError> {
+    match command {
+        ConfigCommand::Schema => {
+            let schema = schemars::schema_for!(Config);
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&schema).expect("Should serialize a JSON schema")
+            );
+        }
+        ConfigCommand::Validate { file } => {
+            let raw =
+                std::fs::read_to_string(&file).map_err(|err| config::ConfigError::IOError {
+                    path: file.clone(),
+                    err,
+                })?;
+            let errors = validate::validate(&raw);
+            for error in &errors {
+                println!("{}:{error}", file.display());
+            }
+            match errors.len() {
+                0 => println!("{} is valid", file.display()),
+                n => return Err(config::ConfigError::Invalid(n)),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Start managing the resources, once this replica is the leader
+async fn start_controller(initial_state: InitialState) -> Result<(), Error> {
+    State::init(initial_state).await?;
+    State::resume_schedule().await?;
+    create_schedule().await.start().await?;
+    Ok(())
 }
 
 async fn process() -> Result<(), Error> {
@@ -119,24 +228,47 @@ async fn process() -> Result<(), Error> {
     //logging setup
     init_logger(cli.verbose, cli.readable_log)?;
 
-    let config = config::parse(cli.config)?;
+    if let Commands::Config(command) = cli.command {
+        return Ok(config_command(command)?);
+    }
+
+    let cli_overrides = cli
+        .command
+        .selector()
+        .map(|selector| Override {
+            path: "controller.selector".to_string(),
+            value: serde_yaml::Value::String(selector.to_string()),
+            layer: Layer::Cli("--selector".to_string()),
+        })
+        .into_iter()
+        .collect();
+    let config = config::parse(cli.config.clone(), cli_overrides)?;
+    reload::apply(&config);
+    if cli.command.dry_run() {
+        dry_run::enable();
+    }
 
     match cli.command {
-        Commands::Start => {
+        Commands::Start { .. } => {
             Deploy::check_kubesleeper().await?;
-            SLEEPINESS_DURATION
-                .set(config.controller.sleepiness_duration)
-                .expect("Failed to set up sleepiness duration");
-            create_schedule(config.controller.refresh_interval)
-                .await
-                .start()
-                .await?;
+            tokio::spawn(reload::watch(cli.config, config.clone()));
+            let initial_state = config.controller.initial_state;
+            tokio::spawn(leader::run(move || async move {
+                if let Err(e) = start_controller(initial_state).await {
+                    eprintln!("Error : {}", e);
+                    process::exit(1);
+                }
+            }));
             server::start(config.server.port).await?;
         }
         Commands::Msg(e) => msg::process(e, config).await?,
-        Commands::Status => {
+        Commands::Status { .. } => {
             Deploy::check_kubesleeper().await?;
-            status().await?
+            status(&config).await?
+        }
+        Commands::Simulate { metrics } => simulate::simulate(&metrics, &config).await?,
+        Commands::Config(_) => {
+            unreachable!("Config commands are run before loading the configuration")
         }
     };
     Ok(())
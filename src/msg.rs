@@ -5,7 +5,16 @@ use crate::{
     Error,
     core::{
         config::Config,
-        resource::{TargetResource, deploy::Deploy, service::Service},
+        resource::{
+            TargetResource,
+            cron_job::CronJob,
+            deploy::Deploy,
+            hpa::{Hpa, ScaleTarget},
+            scalable::Scalable,
+            scaled_object::ScaledObject,
+            service::Service,
+            stateful_set::StatefulSet,
+        },
         state::state_kind::StateKind,
     },
 };
@@ -19,9 +28,17 @@ pub enum Message {
     Set {
         /// The target state to which the cluster will be set
         state: StateKind,
+
+        /// Label selector of the resources to set, overriding the configured one
+        #[arg(long)]
+        selector: Option<String>,
+
+        /// Log the patches of the resources instead of applying them
+        #[arg(long)]
+        dry_run: bool,
     },
 
-    /// Set a specific Deployment or Service to the desired state
+    /// Set a specific Deployment, StatefulSet, Service, CronJob, HPA or ScaledObject to the desired state
     SetRsc {
         /// the kubernetes shortname of resource
         resource_type: ResourceType,
@@ -33,6 +50,10 @@ pub enum Message {
 
         /// The target state to which the resource will be set
         state: StateKind,
+
+        /// Log the patches of the resources instead of applying them
+        #[arg(long)]
+        dry_run: bool,
     },
     /// Start web server alone (without kube resource management)
     StartServer,
@@ -51,25 +72,72 @@ pub mod error {
 }
 
 async fn set(state: StateKind) -> Result<(), Error> {
-    info!("Making all Deploy and Service '{state}'");
+    info!("Making all Deploy, StatefulSet, Service, CronJob, HPA and ScaledObject '{state}'");
     Deploy::check_kubesleeper().await?;
 
+    // Autoscalers of the managed workloads
+    let deploys = Deploy::get_all().await?;
+    let statefulsets = StatefulSet::get_all().await?;
+    let scalables = Scalable::get_all().await?;
+    let targets: Vec<_> = deploys
+        .iter()
+        .map(Deploy::scale_target)
+        .chain(statefulsets.iter().map(StatefulSet::scale_target))
+        .chain(scalables.iter().map(Scalable::scale_target))
+        .collect();
+
     match state {
         StateKind::Asleep => {
-            for deploy in Deploy::get_all().await?.iter_mut() {
-                deploy.sleep().await?
-            }
-            for service in Service::get_all().await?.iter_mut() {
-                service.sleep().await?
-            }
+            set_all::<CronJob>(state).await?;
+            set_hpas(&targets, state).await?;
+            set_all::<Deploy>(state).await?;
+            set_all::<StatefulSet>(state).await?;
+            set_all::<Scalable>(state).await?;
+            set_scaled_objects(&targets, state).await?;
+            set_all::<Service>(state).await
         }
         StateKind::Awake => {
-            for deploy in Deploy::get_all().await?.iter_mut() {
-                deploy.wake().await?
-            }
-            for service in Service::get_all().await?.iter_mut() {
-                service.wake().await?
-            }
+            set_scaled_objects(&targets, state).await?;
+            set_all::<Deploy>(state).await?;
+            set_all::<StatefulSet>(state).await?;
+            set_all::<Scalable>(state).await?;
+            set_hpas(&targets, state).await?;
+            set_all::<Service>(state).await?;
+            set_all::<CronJob>(state).await
+        }
+    }
+}
+
+async fn set_all<T>(state: StateKind) -> Result<(), Error>
+where
+    T: TargetResource<'static>,
+{
+    for resource in T::get_all().await?.iter_mut() {
+        match state {
+            StateKind::Asleep => resource.sleep().await?,
+            StateKind::Awake => resource.wake().await?,
+        }
+    }
+    Ok(())
+}
+
+/// Set HPAs scaling `targets` to `state`
+async fn set_hpas(targets: &[ScaleTarget<'_>], state: StateKind) -> Result<(), Error> {
+    for hpa in Hpa::get_all_scaling(targets).await?.iter_mut() {
+        match state {
+            StateKind::Asleep => hpa.sleep().await?,
+            StateKind::Awake => hpa.wake().await?,
+        }
+    }
+    Ok(())
+}
+
+/// Set KEDA ScaledObjects scaling `targets` to `state`
+async fn set_scaled_objects(targets: &[ScaleTarget<'_>], state: StateKind) -> Result<(), Error> {
+    for scaled_object in ScaledObject::get_all_scaling(targets).await?.iter_mut() {
+        match state {
+            StateKind::Asleep => scaled_object.sleep().await?,
+            StateKind::Awake => scaled_object.wake().await?,
         }
     }
     Ok(())
@@ -79,6 +147,13 @@ async fn set(state: StateKind) -> Result<(), Error> {
 pub enum ResourceType {
     Svc,
     Deploy,
+    Sts,
+    Cj,
+    Hpa,
+    /// KEDA ScaledObject
+    So,
+    /// Any configured scalable kind
+    Scale,
 }
 
 async fn set_rsc_process<T>(state: StateKind, resource_name: String) -> Result<(), Error>
@@ -107,23 +182,26 @@ where
 }
 
 fn dump_config(config: Config) -> Result<(), Error> {
-    println!(
-        "{}",
-        serde_yaml::to_string(&config).unwrap_or(format!("{config:?}"))
-    );
+    print!("{}", config.dump());
     Ok(())
 }
 
 pub async fn process(msg: Message, config: Config) -> Result<(), Error> {
     match msg {
-        Message::Set { state } => set(state).await,
+        Message::Set { state, .. } => set(state).await,
         Message::SetRsc {
             resource_type,
             resource_id,
             state,
+            ..
         } => match resource_type {
             ResourceType::Svc => set_rsc_process::<Service>(state, resource_id).await,
             ResourceType::Deploy => set_rsc_process::<Deploy>(state, resource_id).await,
+            ResourceType::Sts => set_rsc_process::<StatefulSet>(state, resource_id).await,
+            ResourceType::Cj => set_rsc_process::<CronJob>(state, resource_id).await,
+            ResourceType::Hpa => set_rsc_process::<Hpa>(state, resource_id).await,
+            ResourceType::So => set_rsc_process::<ScaledObject>(state, resource_id).await,
+            ResourceType::Scale => set_rsc_process::<Scalable>(state, resource_id).await,
         },
         Message::StartServer => crate::core::server::start(config.server.port)
             .await
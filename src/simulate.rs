@@ -0,0 +1,265 @@
+//! Offline simulation : recorded Traefik metrics are replayed through the state machine on a
+//! virtual clock, to see when groups would have been set asleep or awake.
+//!
+//! Schedules and holidays aren't replayed, only the traffic.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use k8s_openapi::{
+    api::core::v1::Service as K8sService,
+    chrono::{DateTime, Utc},
+};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::core::{
+    config::{Config, duration},
+    ingress::{
+        IngressType,
+        traefik::{Traefik, TraefikServiceResolver},
+    },
+    resource::{management::is_managed, namespaces::is_watched, service::Service},
+    state::{
+        clock,
+        initial_state::InitialState,
+        notification::NotificationKind,
+        state::{GroupState, State},
+        state_kind::StateKind,
+    },
+};
+
+/// File of the metrics directory with the Services of the cluster
+const SERVICES_FILE: &str = "services.yaml";
+
+/// Ingress pod the recorded metrics are counted for
+const RECORDED_POD: &str = "recorded";
+
+pub mod error {
+    use std::path::PathBuf;
+
+    #[derive(Debug, thiserror::Error)]
+    pub enum Simulate {
+        #[error("Can't read {path} : {err}")]
+        IOError { path: PathBuf, err: std::io::Error },
+
+        #[error("Invalid Services file {path} : {err}")]
+        InvalidServices {
+            path: PathBuf,
+            err: serde_yaml::Error,
+        },
+
+        #[error("No metrics dump found in {0}")]
+        NoMetrics(PathBuf),
+
+        #[error(transparent)]
+        Resource(#[from] crate::core::resource::error::Resource),
+    }
+}
+
+/// A list of Services, as printed by `kubectl get services -o yaml`
+#[derive(Deserialize)]
+struct ServiceList {
+    items: Vec<K8sService>,
+}
+
+#[derive(Serialize)]
+struct Decision {
+    time: String,
+    group: String,
+    state: StateKind,
+}
+
+#[derive(Serialize, Default)]
+struct GroupSummary {
+    wakes: usize,
+    sleeps: usize,
+    /// Transitions suppressed by the minimum awake duration or the cooldown
+    suppressed: u64,
+    /// Time spent asleep during the replayed period
+    #[serde(serialize_with = "duration::serialize")]
+    asleep: Duration,
+    #[serde(skip)]
+    asleep_since: Option<DateTime<Utc>>,
+}
+
+impl GroupSummary {
+    fn record(&mut self, time: DateTime<Utc>, state: StateKind) {
+        match state {
+            StateKind::Asleep => {
+                self.sleeps += 1;
+                self.asleep_since = Some(time);
+            }
+            StateKind::Awake => {
+                self.wakes += 1;
+                self.end(time);
+            }
+        }
+    }
+
+    fn end(&mut self, time: DateTime<Utc>) {
+        if let Some(since) = self.asleep_since.take() {
+            self.asleep += (time - since).to_std().unwrap_or_default();
+        }
+    }
+}
+
+fn read(path: &Path) -> Result<String, error::Simulate> {
+    std::fs::read_to_string(path).map_err(|err| error::Simulate::IOError {
+        path: path.to_path_buf(),
+        err,
+    })
+}
+
+/// Managed Services of the Services file of `metrics_dir`
+fn read_services(metrics_dir: &Path) -> Result<Vec<Service>, error::Simulate> {
+    let path = metrics_dir.join(SERVICES_FILE);
+    let list: ServiceList =
+        serde_yaml::from_str(&read(&path)?).map_err(|err| error::Simulate::InvalidServices {
+            path: path.clone(),
+            err,
+        })?;
+    Ok(list
+        .items
+        .iter()
+        .filter(|s| is_watched(*s) && is_managed(*s))
+        .map(Service::try_from)
+        .collect::<Result<_, _>>()?)
+}
+
+/// Time of a metrics dump, from its file name : a Unix timestamp or an RFC 3339 time
+fn dump_time(path: &Path) -> Option<DateTime<Utc>> {
+    let stem = path.file_stem()?.to_str()?;
+    match stem.parse::<i64>() {
+        Ok(seconds) => DateTime::from_timestamp(seconds, 0),
+        Err(_) => DateTime::parse_from_rfc3339(stem).ok().map(|t| t.to_utc()),
+    }
+}
+
+/// Metrics dumps of `metrics_dir`, sorted by time
+fn read_dumps(metrics_dir: &Path) -> Result<Vec<(DateTime<Utc>, PathBuf)>, error::Simulate> {
+    let entries = std::fs::read_dir(metrics_dir).map_err(|err| error::Simulate::IOError {
+        path: metrics_dir.to_path_buf(),
+        err,
+    })?;
+    let mut dumps: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter_map(|path| match dump_time(&path) {
+            Some(time) => Some((time, path)),
+            None => {
+                debug!("Ignoring {} : not a metrics dump", path.display());
+                None
+            }
+        })
+        .collect();
+    dumps.sort();
+    Ok(dumps)
+}
+
+/// Replay the metrics dumps of `metrics_dir` with `config`, printing the decisions taken
+pub async fn simulate(metrics_dir: &Path, config: &Config) -> Result<(), crate::Error> {
+    // they run on the system clock, not on the time of the dumps
+    if !config.controller.schedules.is_empty() || config.controller.holidays.is_some() {
+        warn!(
+            "Schedules and holidays aren't replayed by the simulation : groups only follow the recorded traffic"
+        );
+    }
+
+    let services = read_services(metrics_dir)?;
+    let groups = State::groups_of(State::service_members(&services));
+    let resolver = TraefikServiceResolver::without_crds(&services);
+
+    let dumps = read_dumps(metrics_dir)?;
+    let (Some((first, _)), Some((last, _))) = (dumps.first(), dumps.last()) else {
+        return Err(error::Simulate::NoMetrics(metrics_dir.to_path_buf()).into());
+    };
+    let (first, last) = (*first, *last);
+
+    // the virtual clock starts at the first dump
+    let start = Instant::now();
+    clock::set_virtual(start);
+
+    let mut state = State::default();
+    let mut summaries: BTreeMap<String, GroupSummary> = BTreeMap::new();
+    for group in groups.keys() {
+        let summary = summaries.entry(group.clone()).or_default();
+        if config.controller.initial_state == InitialState::Asleep {
+            state.groups.insert(
+                group.clone(),
+                GroupState::new(StateKind::Asleep, NotificationKind::NoActivity),
+            );
+            summary.asleep_since = Some(first);
+        }
+    }
+
+    let mut timeline = Vec::new();
+    for (i, (time, path)) in dumps.iter().enumerate() {
+        clock::set_virtual(start + (*time - first).to_std().unwrap_or_default());
+        let scrape: HashMap<String, HashMap<String, u64>> =
+            Traefik::parse_prometheus_metrics(read(path)?)
+                .await?
+                .into_iter()
+                .map(|(service, total)| {
+                    (service, HashMap::from([(RECORDED_POD.to_string(), total)]))
+                })
+                .collect();
+
+        // the requests counted before the first dump aren't new
+        if i == 0 {
+            state.metrics.update(&scrape);
+            continue;
+        }
+
+        let notifications: BTreeMap<_, _> = state
+            .apply_metrics(&scrape, &resolver, &services, groups.clone())
+            .into_iter()
+            .collect();
+        for (group, notification) in notifications {
+            let before = state
+                .groups
+                .get(&group)
+                .map_or(StateKind::Awake, |g| g.kind);
+            // resources of awake groups are woken up again when they receive activity
+            let Some(kind) = state
+                .notify(&group, notification)
+                .action
+                .filter(|k| *k != before)
+            else {
+                continue;
+            };
+            summaries
+                .entry(group.clone())
+                .or_default()
+                .record(*time, kind);
+            timeline.push(Decision {
+                time: time.to_rfc3339(),
+                group,
+                state: kind,
+            });
+        }
+    }
+    for (group, summary) in summaries.iter_mut() {
+        summary.end(last);
+        summary.suppressed = state.groups.get(group).map_or(0, |g| g.suppressed);
+    }
+
+    let json = serde_json::json!({
+        "Period" : {
+            "from" : first.to_rfc3339(),
+            "to" : last.to_rfc3339(),
+            "dumps" : dumps.len(),
+        },
+        "Timeline" : timeline,
+        "Groups" : summaries,
+    });
+    println!(
+        "{}",
+        serde_yaml::to_string(&json).unwrap_or_else(|e| format!(
+            "{e} : Simulation structure should be serealizable at this point"
+        ))
+    );
+    Ok(())
+}
@@ -1,55 +1,223 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use serde::Serialize;
 
 use crate::core::{
+    config::Config,
     ingress::IngressType,
     resource::{
         TargetResource,
+        cron_job::CronJob,
         deploy::Deploy,
+        error,
+        hpa::Hpa,
+        replicas::Replicated,
+        scalable::Scalable,
+        scaled_object::ScaledObject,
         service::{Service, ServicePort},
+        stateful_set::StatefulSet,
+    },
+    state::{
+        persistence,
+        schedule::{self, Schedule},
+        sleepiness::Sleepiness,
+        state::State,
     },
 };
+use k8s_openapi::chrono::Utc;
+
+#[derive(Serialize)]
+struct GroupStatus {
+    /// Effective sleepiness duration of the group
+    sleepiness_duration: Sleepiness,
+    /// Transitions suppressed by the minimum awake duration or the cooldown, from the saved state
+    suppressed_transitions: u64,
+}
+
+#[derive(Serialize)]
+struct ScheduleStatus {
+    #[serde(flatten)]
+    schedule: Schedule,
+    /// Next run of the schedule, in the configured timezone
+    next: Option<String>,
+}
 
 #[derive(Serialize)]
 struct DeployStatus {
     id: String,
+    group: String,
     state: String,
     stored_replicas: i32,
 }
 
+// StatefulSets are scaled the same way as Deployments
+type StatefulSetStatus = DeployStatus;
+
 #[derive(Serialize)]
 struct ServiceStatus {
     id: String,
+    group: String,
     state: String,
     stored_selector: HashMap<String, String>,
     stored_ports: Vec<ServicePort>,
 }
 
-pub async fn status() -> Result<(), crate::Error> {
+#[derive(Serialize)]
+struct ScalableStatus {
+    id: String,
+    kind: String,
+    group: String,
+    state: String,
+    stored_replicas: i32,
+}
+
+#[derive(Serialize)]
+struct CronJobStatus {
+    id: String,
+    group: String,
+    state: String,
+    stored_suspend: bool,
+}
+
+#[derive(Serialize)]
+struct HpaStatus {
+    id: String,
+    /// Workload scaled by the HPA, as `{kind}/{name}`
+    target: String,
+    group: String,
+    state: String,
+    stored_scale_up_policy: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ScaledObjectStatus {
+    id: String,
+    /// Workload scaled by the ScaledObject, as `{kind}/{name}`
+    target: String,
+    group: String,
+    state: String,
+    stored_paused_replicas: Option<String>,
+}
+
+/// State of a workload : asleep, awake, or waking up with its ready replicas
+async fn replicas_state(workload: &impl Replicated) -> Result<String, error::Resource> {
+    if workload.is_asleep() {
+        return Ok("asleep".to_string());
+    }
+
+    let ready_replicas_count = workload.get_ready_replicas_count().await?;
+    Ok(match ready_replicas_count == workload.replicas() {
+        true => "awake".to_string(),
+        false => format!(
+            "waking up ({}/{})",
+            ready_replicas_count,
+            workload.replicas()
+        ),
+    })
+}
+
+pub async fn status(config: &Config) -> Result<(), crate::Error> {
+    let group_by = config.controller.group_by;
     let deploys = Deploy::get_all().await?;
-    let mut deploys_status = Vec::new();
-    for d in deploys {
-        let state = if d.is_asleep() {
-            "asleep".to_string()
-        } else {
-            let ready_replicas_count = d.get_ready_replicas_count().await?;
+    let statefulsets = StatefulSet::get_all().await?;
+    let scalables = Scalable::get_all().await?;
 
-            if ready_replicas_count != d.replicas {
-                format!("waking up ({}/{})", ready_replicas_count, d.replicas)
-            } else {
-                "awake".to_string()
-            }
-        };
+    // Autoscalers of managed workloads, reported with the group of their workload
+    let targets: Vec<_> = deploys
+        .iter()
+        .map(|d| (d.scale_target(), d.group(&group_by)))
+        .chain(
+            statefulsets
+                .iter()
+                .map(|s| (s.scale_target(), s.group(&group_by))),
+        )
+        .chain(
+            scalables
+                .iter()
+                .map(|s| (s.scale_target(), s.group(&group_by))),
+        )
+        .collect();
+    let hpas_status: Vec<HpaStatus> = Hpa::get_all()
+        .await?
+        .into_iter()
+        .filter_map(|h| {
+            let (_, group) = targets.iter().find(|(target, _)| h.scales(*target))?;
+            Some(HpaStatus {
+                target: format!("{}/{}", h.target_kind, h.target_name),
+                group: group.clone(),
+                state: if h.is_asleep() { "asleep" } else { "awake" }.to_string(),
+                id: h.id,
+                stored_scale_up_policy: h.store_scale_up_policy,
+            })
+        })
+        .collect();
+    let scaled_objects_status: Vec<ScaledObjectStatus> = ScaledObject::get_all()
+        .await?
+        .into_iter()
+        .filter_map(|so| {
+            let (_, group) = targets.iter().find(|(target, _)| so.scales(*target))?;
+            Some(ScaledObjectStatus {
+                target: format!("{}/{}", so.target_kind, so.target_name),
+                group: group.clone(),
+                state: if so.is_asleep() { "asleep" } else { "awake" }.to_string(),
+                id: so.id,
+                stored_paused_replicas: so.store_paused_replicas,
+            })
+        })
+        .collect();
 
+    let mut deploys_status = Vec::new();
+    for d in deploys {
+        let state = replicas_state(&d).await?;
         deploys_status.push(DeployStatus {
+            group: d.group(&group_by),
             id: d.id,
             state,
             stored_replicas: d.store_replicas,
         });
     }
 
+    let mut statefulsets_status = Vec::new();
+    for s in statefulsets {
+        let state = replicas_state(&s).await?;
+        statefulsets_status.push(StatefulSetStatus {
+            group: s.group(&group_by),
+            id: s.id,
+            state,
+            stored_replicas: s.store_replicas,
+        });
+    }
+
+    let mut scalables_status = Vec::new();
+    for s in scalables {
+        let state = replicas_state(&s).await?;
+        scalables_status.push(ScalableStatus {
+            group: s.group(&group_by),
+            id: s.id,
+            kind: s.kind,
+            state,
+            stored_replicas: s.store_replicas,
+        });
+    }
+
     let services = Service::get_all().await?;
+    let snapshot = persistence::load().await?;
+    let groups_status: BTreeMap<String, GroupStatus> = State::get_all_groups(&services)
+        .await?
+        .into_iter()
+        .map(|(group, sleepiness)| {
+            let suppressed_transitions = snapshot
+                .as_ref()
+                .and_then(|s| s.groups.get(&group))
+                .map(|g| g.suppressed)
+                .unwrap_or_default();
+            let status = GroupStatus {
+                sleepiness_duration: sleepiness,
+                suppressed_transitions,
+            };
+            (group, status)
+        })
+        .collect();
 
     let mut services_status = Vec::new();
     for s in services {
@@ -61,12 +229,44 @@ pub async fn status() -> Result<(), crate::Error> {
 
         services_status.push(ServiceStatus {
             id: s.id.clone(),
+            group: s.group(&group_by),
             state,
             stored_selector: s.store_selector,
             stored_ports: s.store_ports,
         });
     }
 
+    let cronjobs_status: Vec<CronJobStatus> = CronJob::get_all()
+        .await?
+        .into_iter()
+        .map(|c| CronJobStatus {
+            group: c.group(&group_by),
+            state: if c.is_asleep() { "asleep" } else { "awake" }.to_string(),
+            id: c.id,
+            stored_suspend: c.store_suspend,
+        })
+        .collect();
+
+    let timezone = config.controller.timezone;
+    let now = Utc::now().with_timezone(&timezone.0);
+    let schedules_status: Vec<ScheduleStatus> = config
+        .controller
+        .schedules
+        .iter()
+        .map(|s| ScheduleStatus {
+            next: s.next(now).map(|next| next.to_rfc3339()),
+            schedule: s.clone(),
+        })
+        .collect();
+    let current_schedule = schedule::current(&config.controller.schedules, timezone, Utc::now())
+        .map(|s| s.to_string());
+
+    let calendar = &config.calendar;
+    let current_holiday = calendar.current(Utc::now()).map(|h| h.to_string());
+    let next_transition = calendar
+        .next_transition(Utc::now())
+        .map(|t| format!("{} : {t}", t.time().with_timezone(&timezone.0).to_rfc3339()));
+
     let traefik_metrics_pods = crate::core::ingress::traefik::Traefik::get_ingress_pods()
         .await?
         .into_iter()
@@ -74,8 +274,31 @@ pub async fn status() -> Result<(), crate::Error> {
         .collect::<Vec<_>>();
 
     let json = serde_json::json!({
+        "Groups" : groups_status,
+        "Schedules" : {
+            "timezone" : timezone.to_string(),
+            "current" : current_schedule,
+            "schedules" : schedules_status,
+        },
+        "Holidays" : {
+            "file" : config.controller.holidays,
+            "current" : current_holiday,
+            "next_transition" : next_transition,
+        },
         "Deployments" : deploys_status,
+        "StatefulSets" : statefulsets_status,
+        "Scalables" : scalables_status,
         "Services" : services_status,
+        "CronJobs" : cronjobs_status,
+        "HorizontalPodAutoscalers" : hpas_status,
+        "ScaledObjects" : scaled_objects_status,
+        "Unmanaged" : {
+            "Deployments" : Deploy::get_unmanaged_ids().await?,
+            "StatefulSets" : StatefulSet::get_unmanaged_ids().await?,
+            "Scalables" : Scalable::get_unmanaged_ids().await?,
+            "Services" : Service::get_unmanaged_ids().await?,
+            "CronJobs" : CronJob::get_unmanaged_ids().await?,
+        },
         "Metric Pods": {
             "Traefik" : traefik_metrics_pods
         }